@@ -1,4 +1,8 @@
-INSERT INTO DisasterStatus(chatid, userid, points)
-VALUES ($1, $2, 1)
-       ON CONFLICT(chatid, userid)
-       DO UPDATE SET points = DisasterStatus.points + 1
+WITH point AS (
+  INSERT INTO DisasterPoints(chatid, giverid, receiverid, instant, reason, msgid, category, amount)
+  VALUES ($1, $3, $2, $4, $5, $6, $7, $8)
+)
+INSERT INTO DisasterStatus(chatid, userid, category, points)
+VALUES ($1, $2, $7, $8)
+       ON CONFLICT(chatid, userid, category)
+       DO UPDATE SET points = DisasterStatus.points + $8
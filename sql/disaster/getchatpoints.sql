@@ -1,5 +1,4 @@
 SELECT points, coalesce(userid, 0)
   FROM DisasterStatus
- WHERE chatid = $1
- GROUP BY userid, points
+ WHERE chatid = $1 AND category = $2 AND points <> 0
  ORDER BY points DESC
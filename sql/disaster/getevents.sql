@@ -0,0 +1,5 @@
+SELECT giverid, receiverid, instant, amount
+  FROM DisasterPoints
+ WHERE chatid = $1 AND category = $2
+   AND instant >= $3 AND ($4::BIGINT IS NULL OR instant < $4)
+ ORDER BY instant, id
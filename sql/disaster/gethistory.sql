@@ -0,0 +1,6 @@
+SELECT giverid, receiverid, instant, reason, amount, COUNT(*) OVER ()
+  FROM DisasterPoints
+ WHERE chatid = $1 AND category = $6
+   AND instant >= $4 AND ($5::BIGINT IS NULL OR instant < $5)
+ ORDER BY instant DESC, id DESC
+ LIMIT $2 OFFSET $3
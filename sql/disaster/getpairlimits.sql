@@ -0,0 +1,9 @@
+-- When receiver $2 last got a point of category $4, and how many of them $3 gave $2 at or after
+-- $5
+SELECT (SELECT MAX(instant)
+          FROM DisasterPoints
+         WHERE chatid = $1 AND receiverid = $2 AND category = $4),
+       (SELECT COUNT(*)
+          FROM DisasterPoints
+         WHERE chatid = $1 AND giverid = $3 AND receiverid = $2 AND category = $4
+           AND instant >= $5)
@@ -0,0 +1,21 @@
+-- Points members have in a season which aren't in DisasterPoints, like the ones given before
+-- every point was logged
+WITH totals AS (
+  SELECT coalesce(userid, 0) AS userid, points
+    FROM DisasterStatus
+   WHERE chatid = $1 AND category = $2 AND $4::BIGINT IS NULL
+   UNION ALL
+  SELECT userid, points
+    FROM DisasterStandings
+   WHERE chatid = $1 AND category = $2 AND season = $5 AND $4::BIGINT IS NOT NULL
+), logged AS (
+  SELECT receiverid AS userid, SUM(amount)::BIGINT AS points
+    FROM DisasterPoints
+   WHERE chatid = $1 AND category = $2
+     AND instant >= $3 AND ($4::BIGINT IS NULL OR instant < $4)
+   GROUP BY receiverid
+)
+SELECT t.userid, t.points - coalesce(l.points, 0)
+  FROM totals t
+  LEFT JOIN logged l ON l.userid = t.userid
+ WHERE t.points <> coalesce(l.points, 0)
@@ -1,3 +1,3 @@
 SELECT coalesce(points, 0)
   FROM DisasterStatus
- WHERE chatid = $1 AND userid = $2
+ WHERE chatid = $1 AND userid = $2 AND category = $3
@@ -0,0 +1,21 @@
+-- Takes back the last point of category $4 which $2 gave in chat $1, if it was given at or
+-- after $3. Points whose receiver has no total anymore are left alone, as nothing would be
+-- taken back from them.
+WITH revoked AS (
+  DELETE FROM DisasterPoints p
+   WHERE id = (SELECT id
+                 FROM DisasterPoints
+                WHERE chatid = $1 AND giverid = $2 AND category = $4
+                ORDER BY instant DESC, id DESC
+                LIMIT 1)
+     AND instant >= $3
+     AND EXISTS (SELECT 1
+                   FROM DisasterStatus s
+                  WHERE s.chatid = $1 AND s.userid = p.receiverid AND s.category = $4)
+  RETURNING receiverid, amount
+)
+UPDATE DisasterStatus
+   SET points = points - revoked.amount
+  FROM revoked
+ WHERE chatid = $1 AND userid = revoked.receiverid AND category = $4
+RETURNING userid, points
@@ -0,0 +1,9 @@
+SELECT fallback, COUNT(*)
+  FROM CustomEmojiLogs
+ WHERE chatid = $1
+   AND ($2::BIGINT IS NULL OR userid = $2)
+   AND ($3::BIGINT IS NULL OR EXISTS (
+        SELECT 1 FROM MessageLogs m
+         WHERE m.chatid = $1 AND m.msgid = CustomEmojiLogs.msgid
+           AND COALESCE(m.threadid, 1) = $3))
+ GROUP BY fallback
@@ -0,0 +1,5 @@
+SELECT message
+  FROM MessageLogs
+ WHERE chatid = $1
+   AND ($2::BIGINT IS NULL OR userid = $2)
+   AND ($3::BIGINT IS NULL OR COALESCE(threadid, 1) = $3)
@@ -0,0 +1,6 @@
+SELECT emoji, COUNT(*)
+  FROM StickerLogs
+ WHERE chatid = $1 AND emoji IS NOT NULL
+   AND ($2::BIGINT IS NULL OR userid = $2)
+   AND ($3::BIGINT IS NULL OR COALESCE(threadid, 1) = $3)
+ GROUP BY emoji
@@ -0,0 +1,3 @@
+INSERT INTO CustomEmojiLogs(chatid, userid, msgid, emojiid, fallback, instant)
+SELECT $1, $2, $3, emojiid, fallback, $4
+  FROM UNNEST($5::TEXT[], $6::TEXT[]) AS e(emojiid, fallback)
@@ -7,8 +7,13 @@ SELECT MessageLogs.userid,
                       EditLogs.userid
                  FROM EditLogs
                 WHERE EditLogs.chatid = $1
+                  AND ($2::BIGINT IS NULL OR EXISTS (
+                        SELECT 1 FROM MessageLogs m
+                         WHERE m.chatid = $1 AND m.msgid = EditLogs.msgid
+                           AND COALESCE(m.threadid, 1) = $2))
                 GROUP BY EditLogs.userid
          ) AS edits ON MessageLogs.userid = edits.userid
  WHERE MessageLogs.chatid = $1
+   AND ($2::BIGINT IS NULL OR COALESCE(MessageLogs.threadid, 1) = $2)
  GROUP BY MessageLogs.userid, edits.totalEdits, edits.uniqueEdits
  ORDER BY percentage DESC
@@ -1,5 +1,6 @@
 SELECT userid, COUNT(message) AS messages
   FROM MessageLogs
  WHERE chatid = $1
+   AND ($2::BIGINT IS NULL OR COALESCE(threadid, 1) = $2)
  GROUP BY userid
  ORDER BY messages DESC
@@ -1,3 +1,4 @@
 SELECT COUNT(message), MIN(instant)
   FROM MessageLogs
  WHERE chatid = $1
+   AND ($2::BIGINT IS NULL OR COALESCE(threadid, 1) = $2)
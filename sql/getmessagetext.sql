@@ -1,4 +1,5 @@
 SELECT message, userid, instant
   FROM MessageLogs
  WHERE chatid = $1
+   AND ($2::BIGINT IS NULL OR COALESCE(threadid, 1) = $2)
  ORDER BY instant ASC
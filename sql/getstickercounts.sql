@@ -1,6 +1,9 @@
-SELECT hash, COUNT(*) AS uses
+SELECT MIN(fileid), MIN(thumbnailid), BOOL_OR(animated), MAX(phash), COUNT(*) AS uses, MIN(packname), hash, MIN(emoji)
   FROM StickerLogs
  WHERE chatid = $1 AND instant > $2
+   AND ($3::BIGINT IS NULL OR userid = $3)
+   AND ($4::TEXT IS NULL OR packname = $4)
+   AND ($5::TEXT IS NULL OR replace(emoji, U&'\FE0F', '') = $5)
+   AND ($6::BIGINT IS NULL OR COALESCE(threadid, 1) = $6)
  GROUP BY hash
  ORDER BY uses DESC
- LIMIT 25
@@ -1,4 +0,0 @@
-SELECT fileid
-  FROM StickerLogs
- WHERE hash = $1
- LIMIT 1
@@ -2,3 +2,7 @@ SELECT COUNT(*) AS totalStickers,
        COUNT(DISTINCT packname) AS packs
   FROM StickerLogs
  WHERE chatid = $1 AND instant > $2
+   AND ($3::BIGINT IS NULL OR userid = $3)
+   AND ($4::TEXT IS NULL OR packname = $4)
+   AND ($5::TEXT IS NULL OR replace(emoji, U&'\FE0F', '') = $5)
+   AND ($6::BIGINT IS NULL OR COALESCE(threadid, 1) = $6)
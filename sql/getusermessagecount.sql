@@ -1,3 +1,4 @@
 SELECT COUNT(message) AS messages
   FROM MessageLogs
  WHERE chatid = $1 AND userid = $2
+   AND ($3::BIGINT IS NULL OR COALESCE(threadid, 1) = $3)
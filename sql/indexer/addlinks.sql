@@ -0,0 +1,3 @@
+INSERT INTO LinkLogs(chatid, userid, msgid, instant, url, domain)
+SELECT $1, $2, $3, $4, url, domain
+  FROM UNNEST($5::TEXT[], $6::TEXT[]) AS found(url, domain)
@@ -0,0 +1,5 @@
+INSERT INTO NGramCounts(chatid, userid, threadid, n, ngram, uses)
+SELECT $1, $2, $3, n, ngram, uses
+  FROM UNNEST($4::SMALLINT[], $5::TEXT[], $6::BIGINT[]) AS added(n, ngram, uses)
+    ON CONFLICT(chatid, userid, threadid, n, ngram) DO
+UPDATE SET uses = NGramCounts.uses + EXCLUDED.uses
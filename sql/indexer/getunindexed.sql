@@ -0,0 +1,5 @@
+SELECT chatid, userid, msgid, message, indexversion, instant, threadid
+  FROM MessageLogs
+ WHERE indexversion < $1
+ LIMIT $2
+   FOR UPDATE
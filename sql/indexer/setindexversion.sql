@@ -0,0 +1,3 @@
+UPDATE MessageLogs
+   SET indexversion = $3
+ WHERE chatid = $1 AND msgid = $2
@@ -0,0 +1,5 @@
+SELECT userid, instant
+  FROM LinkLogs
+ WHERE chatid = $1 AND url = $2 AND msgid <> $3 AND instant >= $4
+ ORDER BY instant
+ LIMIT 1
@@ -0,0 +1,12 @@
+SELECT url, COUNT(*) AS shared
+  FROM LinkLogs
+ WHERE chatid = $1
+   AND ($2::BIGINT IS NULL OR userid = $2)
+   AND ($4::BIGINT IS NULL OR EXISTS (
+        SELECT 1 FROM MessageLogs m
+         WHERE m.chatid = $1 AND m.msgid = LinkLogs.msgid
+           AND COALESCE(m.threadid, 1) = $4))
+ GROUP BY url
+HAVING COUNT(*) > 1
+ ORDER BY shared DESC, url
+ LIMIT $3
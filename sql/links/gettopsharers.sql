@@ -0,0 +1,10 @@
+SELECT userid, COUNT(*) AS shared
+  FROM LinkLogs
+ WHERE chatid = $1
+   AND ($3::BIGINT IS NULL OR EXISTS (
+        SELECT 1 FROM MessageLogs m
+         WHERE m.chatid = $1 AND m.msgid = LinkLogs.msgid
+           AND COALESCE(m.threadid, 1) = $3))
+ GROUP BY userid
+ ORDER BY shared DESC
+ LIMIT $2
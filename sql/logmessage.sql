@@ -1,2 +1,2 @@
-INSERT INTO Messagelogs(msgid, chatid, userid, message, instant)
-VALUES($1,$2,$3,$4,$5)
+INSERT INTO Messagelogs(msgid, chatid, userid, message, instant, indexversion, threadid)
+VALUES($1,$2,$3,$4,$5,$6,$7)
@@ -1,2 +1,2 @@
-INSERT INTO StickerLogs(userid, chatid, msgid, fileid, emoji, packname, instant, hash)
-VALUES($1,$2,$3,$4,$5,$6,$7,$8)
+INSERT INTO StickerLogs(userid, chatid, msgid, fileid, emoji, packname, instant, hash, phash, animated, thumbnailid, threadid)
+VALUES($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12)
@@ -0,0 +1,8 @@
+-- Both a service message and a chat member update can tell about the same change
+INSERT INTO MembershipEvents(chatid, userid, joined, instant)
+SELECT $1, $2, $3, $4
+ WHERE $3 IS DISTINCT FROM (SELECT joined
+                              FROM MembershipEvents
+                             WHERE chatid = $1 AND userid = $2
+                             ORDER BY instant DESC
+                             LIMIT 1)
@@ -0,0 +1,3 @@
+SELECT id, firstname, lastname, username
+  FROM LastUserData
+ WHERE chatid = $1 AND id = ANY($2)
@@ -0,0 +1,7 @@
+-- Users who most recently left the chat
+SELECT userid
+  FROM (SELECT DISTINCT ON (userid) userid, joined
+          FROM MembershipEvents
+         WHERE chatid = $1
+         ORDER BY userid, instant DESC) AS latest
+ WHERE NOT joined
@@ -0,0 +1,8 @@
+-- Every name the users of a chat go by or used to go by, and whether it's the current one
+SELECT id, firstname, lastname, username, TRUE
+  FROM LastUserData
+ WHERE chatid = $1
+ UNION ALL
+SELECT userid, firstname, lastname, username, FALSE
+  FROM UserNameHistory
+ WHERE chatid = $1
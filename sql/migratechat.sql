@@ -0,0 +1,86 @@
+-- Moves everything stored about chat $1 over to chat $2, for when a group becomes a supergroup.
+-- Rows which are keyed by chat are copied and then deleted, merging with what's already there.
+WITH messages AS (
+  UPDATE MessageLogs SET chatid = $2 WHERE chatid = $1
+), edits AS (
+  UPDATE EditLogs SET chatid = $2 WHERE chatid = $1
+), stickers AS (
+  UPDATE StickerLogs SET chatid = $2 WHERE chatid = $1
+), commands AS (
+  UPDATE CommandLogs SET chatid = $2 WHERE chatid = $1
+), customemoji AS (
+  UPDATE CustomEmojiLogs SET chatid = $2 WHERE chatid = $1
+), links AS (
+  UPDATE LinkLogs SET chatid = $2 WHERE chatid = $1
+), photos AS (
+  UPDATE PhotoLogs SET chatid = $2 WHERE chatid = $1
+), members AS (
+  UPDATE MembershipEvents SET chatid = $2 WHERE chatid = $1
+), names AS (
+  UPDATE UserNameHistory SET chatid = $2 WHERE chatid = $1
+), users AS (
+  INSERT INTO LastUserData(id, chatid, firstname, lastname, username)
+  SELECT id, $2, firstname, lastname, username FROM LastUserData WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), olduserdata AS (
+  DELETE FROM LastUserData WHERE chatid = $1
+), disasters AS (
+  INSERT INTO DisasterStatus(userid, chatid, category, points)
+  SELECT userid, $2, category, points FROM DisasterStatus WHERE chatid = $1
+      ON CONFLICT(chatid, userid, category) DO
+  UPDATE SET points = DisasterStatus.points + EXCLUDED.points
+), olddisasters AS (
+  DELETE FROM DisasterStatus WHERE chatid = $1
+), disasterpoints AS (
+  UPDATE DisasterPoints SET chatid = $2 WHERE chatid = $1
+), seasons AS (
+  INSERT INTO DisasterSeasons(chatid, season, started, ended)
+  SELECT $2, season, started, ended FROM DisasterSeasons WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), oldseasons AS (
+  DELETE FROM DisasterSeasons WHERE chatid = $1
+), standings AS (
+  INSERT INTO DisasterStandings(chatid, season, userid, category, points)
+  SELECT $2, season, userid, category, points FROM DisasterStandings WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), oldstandings AS (
+  DELETE FROM DisasterStandings WHERE chatid = $1
+), categories AS (
+  INSERT INTO PointCategories(chatid, name, emoji, cooldown, allowself, replies)
+  SELECT $2, name, emoji, cooldown, allowself, replies FROM PointCategories WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), oldcategories AS (
+  DELETE FROM PointCategories WHERE chatid = $1
+), settings AS (
+  INSERT INTO ChatSettings(chatid, name, value, changed)
+  SELECT $2, name, value, changed FROM ChatSettings WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), oldsettings AS (
+  DELETE FROM ChatSettings WHERE chatid = $1
+), ngrams AS (
+  INSERT INTO NGramCounts(chatid, userid, threadid, n, ngram, uses)
+  SELECT $2, userid, threadid, n, ngram, uses FROM NGramCounts WHERE chatid = $1
+      ON CONFLICT(chatid, userid, threadid, n, ngram) DO
+  UPDATE SET uses = NGramCounts.uses + EXCLUDED.uses
+), oldngrams AS (
+  DELETE FROM NGramCounts WHERE chatid = $1
+), topics AS (
+  INSERT INTO ForumTopics(chatid, threadid, name)
+  SELECT $2, threadid, name FROM ForumTopics WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), oldtopics AS (
+  DELETE FROM ForumTopics WHERE chatid = $1
+), reactions AS (
+  INSERT INTO ReactionLogs(chatid, msgid, userid, reaction, instant)
+  SELECT $2, msgid, userid, reaction, instant FROM ReactionLogs WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), oldreactions AS (
+  DELETE FROM ReactionLogs WHERE chatid = $1
+), reactioncounts AS (
+  INSERT INTO ReactionCounts(chatid, msgid, reaction, count)
+  SELECT $2, msgid, reaction, count FROM ReactionCounts WHERE chatid = $1
+      ON CONFLICT DO NOTHING
+), oldreactioncounts AS (
+  DELETE FROM ReactionCounts WHERE chatid = $1
+)
+SELECT 1
@@ -0,0 +1,233 @@
+-- Everything created before migrations were tracked. Kept idempotent so databases set up back then
+-- pick it up without changes.
+CREATE TABLE IF NOT EXISTS MessageLogs (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL,
+  message TEXT NOT NULL,
+  instant BIGINT NOT NULL
+);
+
+ALTER TABLE MessageLogs
+  ADD COLUMN IF NOT EXISTS textsearch TSVECTOR
+  GENERATED ALWAYS AS (to_tsvector('simple', message)) STORED;
+CREATE INDEX IF NOT EXISTS MessageLogsTextSearch ON MessageLogs USING GIN(textsearch);
+-- Which version of the indexer has processed each message, see indexer.rs
+ALTER TABLE MessageLogs ADD COLUMN IF NOT EXISTS indexversion SMALLINT NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS MessageLogsIndexVersion ON MessageLogs(indexversion);
+-- Forum topic of the message, NULL outside of forums and in the General topic
+ALTER TABLE MessageLogs ADD COLUMN IF NOT EXISTS threadid BIGINT;
+
+CREATE TABLE IF NOT EXISTS EditLogs (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS StickerLogs (
+  userid BIGINT NOT NULL,
+  chatid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL,
+  fileid TEXT NOT NULL,
+  packname TEXT,
+  emoji TEXT,
+  instant BIGINT NOT NULL,
+  hash BYTEA NOT NULL
+);
+-- Perceptual hash of the sticker image, see image.rs
+ALTER TABLE StickerLogs ADD COLUMN IF NOT EXISTS phash BIGINT;
+-- Animated and video stickers can only be shown through their thumbnail
+ALTER TABLE StickerLogs ADD COLUMN IF NOT EXISTS animated BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE StickerLogs ADD COLUMN IF NOT EXISTS thumbnailid TEXT;
+ALTER TABLE StickerLogs ADD COLUMN IF NOT EXISTS threadid BIGINT;
+
+CREATE TABLE IF NOT EXISTS LastUserData (
+  id BIGINT,
+  chatid BIGINT,
+  firstname TEXT NOT NULL,
+  lastname TEXT,
+  username TEXT,
+  PRIMARY KEY(id, chatid)
+);
+
+CREATE TABLE IF NOT EXISTS DisasterStatus (
+  userid BIGINT,
+  chatid BIGINT,
+  points BIGINT,
+  PRIMARY KEY(chatid, userid)
+);
+
+-- Every point given, while DisasterStatus keeps the totals which include points from before
+CREATE TABLE IF NOT EXISTS DisasterPoints (
+  id BIGSERIAL PRIMARY KEY,
+  chatid BIGINT NOT NULL,
+  giverid BIGINT NOT NULL,
+  receiverid BIGINT NOT NULL,
+  instant BIGINT NOT NULL,
+  reason TEXT,
+  msgid BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS DisasterPointsChat ON DisasterPoints(chatid, instant);
+
+-- A chat without seasons is in season 1, which started when the first point was given
+CREATE TABLE IF NOT EXISTS DisasterSeasons (
+  chatid BIGINT NOT NULL,
+  season BIGINT NOT NULL,
+  started BIGINT NOT NULL,
+  ended BIGINT,
+  PRIMARY KEY(chatid, season)
+);
+
+-- Points of ended seasons, DisasterStatus has those of the current one
+CREATE TABLE IF NOT EXISTS DisasterStandings (
+  chatid BIGINT NOT NULL,
+  season BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  points BIGINT NOT NULL,
+  PRIMARY KEY(chatid, season, userid)
+);
+
+-- Kinds of points chat admins have added besides disaster points, or disaster points with
+-- other rules. Points of every kind are stored along with disaster points.
+CREATE TABLE IF NOT EXISTS PointCategories (
+  chatid BIGINT NOT NULL,
+  name TEXT NOT NULL,
+  emoji TEXT NOT NULL,
+  cooldown BIGINT NOT NULL, -- hours
+  allowself BOOLEAN NOT NULL,
+  replies BOOLEAN NOT NULL, -- +1 and -1 replies give and take points
+  PRIMARY KEY(chatid, name)
+);
+ALTER TABLE DisasterStatus ADD COLUMN IF NOT EXISTS category TEXT NOT NULL DEFAULT 'disaster';
+ALTER TABLE DisasterStatus DROP CONSTRAINT IF EXISTS disasterstatus_pkey;
+CREATE UNIQUE INDEX IF NOT EXISTS DisasterStatusCategory
+    ON DisasterStatus(chatid, userid, category);
+ALTER TABLE DisasterPoints ADD COLUMN IF NOT EXISTS category TEXT NOT NULL DEFAULT 'disaster';
+ALTER TABLE DisasterPoints ADD COLUMN IF NOT EXISTS amount BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE DisasterStandings ADD COLUMN IF NOT EXISTS category TEXT NOT NULL DEFAULT 'disaster';
+ALTER TABLE DisasterStandings DROP CONSTRAINT IF EXISTS disasterstandings_pkey;
+CREATE UNIQUE INDEX IF NOT EXISTS DisasterStandingsCategory
+    ON DisasterStandings(chatid, season, userid, category);
+
+CREATE TABLE IF NOT EXISTS CommandNames (
+  commandId BIGSERIAL PRIMARY KEY,
+  command TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS CommandLogs (
+  userid BIGINT NOT NULL,
+  chatid BIGINT NOT NULL,
+  command BIGINT NOT NULL REFERENCES CommandNames(commandId),
+  logtime TIMESTAMP WITH TIME ZONE
+);
+
+CREATE TABLE IF NOT EXISTS ChatSettings (
+  chatid BIGINT NOT NULL,
+  name TEXT NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY(chatid, name)
+);
+
+CREATE TABLE IF NOT EXISTS NGramCounts (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  n SMALLINT NOT NULL,
+  ngram TEXT NOT NULL,
+  uses BIGINT NOT NULL,
+  PRIMARY KEY(chatid, userid, n, ngram)
+);
+CREATE INDEX IF NOT EXISTS NGramCountsChat ON NGramCounts(chatid, n, ngram);
+
+-- Custom emoji can't be told apart from their fallback emoji by the text alone
+CREATE TABLE IF NOT EXISTS CustomEmojiLogs (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL,
+  emojiid TEXT NOT NULL,
+  fallback TEXT NOT NULL,
+  instant BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS CustomEmojiLogsChat ON CustomEmojiLogs(chatid, userid);
+
+-- Normalized links found in messages, filled in by the indexer
+CREATE TABLE IF NOT EXISTS LinkLogs (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL,
+  url TEXT NOT NULL,
+  domain TEXT NOT NULL,
+  instant BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS LinkLogsUrl ON LinkLogs(chatid, url);
+CREATE INDEX IF NOT EXISTS LinkLogsDomain ON LinkLogs(chatid, domain);
+
+CREATE TABLE IF NOT EXISTS PhotoLogs (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL,
+  fileid TEXT NOT NULL,
+  phash BIGINT,
+  instant BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS PhotoLogsChat ON PhotoLogs(chatid, instant);
+
+-- Sticker pack metadata from getStickerSet, refreshed now and then
+CREATE TABLE IF NOT EXISTS StickerPacks (
+  name TEXT PRIMARY KEY,
+  title TEXT NOT NULL,
+  stickercount INTEGER NOT NULL,
+  thumbnailid TEXT,
+  fetched BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ForumTopics (
+  chatid BIGINT NOT NULL,
+  threadid BIGINT NOT NULL,
+  name TEXT NOT NULL,
+  PRIMARY KEY(chatid, threadid)
+);
+
+-- Reactions users currently have on each message
+CREATE TABLE IF NOT EXISTS ReactionLogs (
+  chatid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  reaction TEXT NOT NULL,
+  instant BIGINT NOT NULL,
+  PRIMARY KEY(chatid, msgid, userid, reaction)
+);
+
+-- Reaction totals of messages, which is all Telegram tells about anonymous reactions
+CREATE TABLE IF NOT EXISTS ReactionCounts (
+  chatid BIGINT NOT NULL,
+  msgid BIGINT NOT NULL,
+  reaction TEXT NOT NULL,
+  count BIGINT NOT NULL,
+  PRIMARY KEY(chatid, msgid, reaction)
+);
+
+-- Users joining and leaving chats
+CREATE TABLE IF NOT EXISTS MembershipEvents (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  joined BOOLEAN NOT NULL,
+  instant BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS MembershipEventsUser ON MembershipEvents(chatid, userid, instant);
+
+CREATE TABLE IF NOT EXISTS UserNameHistory (
+  chatid BIGINT NOT NULL,
+  userid BIGINT NOT NULL,
+  firstname TEXT NOT NULL,
+  lastname TEXT,
+  username TEXT,
+  instant BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS UserNameHistoryUser ON UserNameHistory(chatid, userid, instant);
+-- Keep the names known from before the history existed, with an unknown time
+INSERT INTO UserNameHistory(chatid, userid, firstname, lastname, username, instant)
+SELECT chatid, id, firstname, lastname, username, 0
+  FROM LastUserData u
+ WHERE NOT EXISTS (SELECT 1
+                     FROM UserNameHistory h
+                    WHERE h.chatid = u.chatid AND h.userid = u.id);
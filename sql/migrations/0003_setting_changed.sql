@@ -0,0 +1,14 @@
+-- When each setting was last changed, so monthly seasons can count from when they were turned on
+ALTER TABLE ChatSettings ADD COLUMN changed BIGINT NOT NULL DEFAULT 0;
+
+-- Turning on monthly seasons used to start the first season right then, hiding the points given
+-- before it from the history. Remember that as when they were turned on instead.
+UPDATE ChatSettings s
+   SET changed = d.started
+  FROM DisasterSeasons d
+ WHERE s.name = 'seasons' AND d.chatid = s.chatid AND d.season = 1 AND d.ended IS NULL;
+UPDATE ChatSettings s
+   SET changed = EXTRACT(EPOCH FROM now())::BIGINT
+ WHERE s.name = 'seasons'
+   AND NOT EXISTS (SELECT 1 FROM DisasterSeasons d WHERE d.chatid = s.chatid);
+UPDATE DisasterSeasons SET started = 0 WHERE season = 1;
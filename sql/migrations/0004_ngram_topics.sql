@@ -0,0 +1,6 @@
+-- N-gram counts are kept per forum topic and include single words now. Topics weren't known for
+-- the old counts, so they are thrown away and rebuilt from the message logs by the indexer.
+TRUNCATE NGramCounts;
+ALTER TABLE NGramCounts ADD COLUMN threadid BIGINT NOT NULL;
+ALTER TABLE NGramCounts DROP CONSTRAINT ngramcounts_pkey;
+ALTER TABLE NGramCounts ADD PRIMARY KEY(chatid, userid, threadid, n, ngram);
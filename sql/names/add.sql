@@ -0,0 +1,12 @@
+-- Only a change of name is recorded, not every message
+INSERT INTO UserNameHistory(chatid, userid, firstname, lastname, username, instant)
+SELECT $1, $2, $3, $4, $5, $6
+ WHERE NOT EXISTS (SELECT 1
+                     FROM (SELECT firstname, lastname, username
+                             FROM UserNameHistory
+                            WHERE chatid = $1 AND userid = $2
+                            ORDER BY instant DESC
+                            LIMIT 1) latest
+                    WHERE latest.firstname = $3
+                      AND latest.lastname IS NOT DISTINCT FROM $4
+                      AND latest.username IS NOT DISTINCT FROM $5)
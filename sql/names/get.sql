@@ -0,0 +1,4 @@
+SELECT firstname, lastname, username, instant
+  FROM UserNameHistory
+ WHERE chatid = $1 AND userid = $2
+ ORDER BY instant
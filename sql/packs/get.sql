@@ -0,0 +1,3 @@
+SELECT title, stickercount, thumbnailid, fetched
+  FROM StickerPacks
+ WHERE name = $1
@@ -0,0 +1,7 @@
+-- The most used pack of every user in the chat
+SELECT DISTINCT ON (userid) userid, packname, COUNT(*) AS uses
+  FROM StickerLogs
+ WHERE chatid = $1 AND packname IS NOT NULL
+   AND ($2::BIGINT IS NULL OR COALESCE(threadid, 1) = $2)
+ GROUP BY userid, packname
+ ORDER BY userid, uses DESC, packname
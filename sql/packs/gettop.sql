@@ -0,0 +1,8 @@
+SELECT packname, COUNT(*) AS uses, COUNT(DISTINCT hash) AS used
+  FROM StickerLogs
+ WHERE chatid = $1 AND packname IS NOT NULL
+   AND ($2::BIGINT IS NULL OR userid = $2)
+   AND ($4::BIGINT IS NULL OR COALESCE(threadid, 1) = $4)
+ GROUP BY packname
+ ORDER BY uses DESC, packname
+ LIMIT $3
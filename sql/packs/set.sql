@@ -0,0 +1,7 @@
+INSERT INTO StickerPacks(name, title, stickercount, thumbnailid, fetched)
+VALUES($1, $2, $3, $4, $5)
+    ON CONFLICT(name) DO
+UPDATE SET title = EXCLUDED.title,
+           stickercount = EXCLUDED.stickercount,
+           thumbnailid = EXCLUDED.thumbnailid,
+           fetched = EXCLUDED.fetched
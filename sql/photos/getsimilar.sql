@@ -0,0 +1,8 @@
+-- Earliest photo within the window whose hash is at most $5 bits away
+SELECT userid, instant
+  FROM PhotoLogs
+ WHERE chatid = $1 AND msgid <> $2 AND instant >= $3
+   AND phash IS NOT NULL
+   AND length(replace(((phash # $4)::BIT(64))::TEXT, '0', '')) <= $5
+ ORDER BY instant
+ LIMIT 1
@@ -0,0 +1,2 @@
+INSERT INTO PhotoLogs(chatid, userid, msgid, fileid, phash, instant)
+VALUES($1,$2,$3,$4,$5,$6)
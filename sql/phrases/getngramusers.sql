@@ -0,0 +1,6 @@
+SELECT userid, SUM(uses)::BIGINT AS total
+  FROM NGramCounts
+ WHERE chatid = $1 AND n = $2 AND ngram = $3
+   AND ($4::BIGINT IS NULL OR threadid = $4)
+ GROUP BY userid
+ ORDER BY total DESC
@@ -0,0 +1,8 @@
+SELECT ngram, SUM(uses)::BIGINT AS total
+  FROM NGramCounts
+ WHERE chatid = $1 AND n = $2
+   AND ($3::BIGINT IS NULL OR userid = $3)
+   AND ($5::BIGINT IS NULL OR threadid = $5)
+ GROUP BY ngram
+ ORDER BY total DESC, ngram
+ LIMIT $4
@@ -0,0 +1,4 @@
+SELECT name, emoji, cooldown, allowself, replies
+  FROM PointCategories
+ WHERE chatid = $1
+ ORDER BY name
@@ -0,0 +1,2 @@
+DELETE FROM PointCategories
+ WHERE chatid = $1 AND name = $2
@@ -0,0 +1,4 @@
+INSERT INTO PointCategories(chatid, name, emoji, cooldown, allowself, replies)
+VALUES ($1, $2, $3, $4, $5, $6)
+       ON CONFLICT(chatid, name) DO
+UPDATE SET emoji = $3, cooldown = $4, allowself = $5, replies = $6
@@ -0,0 +1,3 @@
+INSERT INTO ReactionLogs(chatid, msgid, userid, reaction, instant)
+VALUES($1, $2, $3, $4, $5)
+    ON CONFLICT DO NOTHING
@@ -0,0 +1,2 @@
+DELETE FROM ReactionCounts
+ WHERE chatid = $1 AND msgid = $2
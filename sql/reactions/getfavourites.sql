@@ -0,0 +1,10 @@
+-- The most used reaction of every user in the chat
+SELECT DISTINCT ON (userid) userid, reaction, COUNT(*) AS uses
+  FROM ReactionLogs
+ WHERE chatid = $1
+   AND ($2::BIGINT IS NULL OR msgid IN (
+        SELECT msgid FROM MessageLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $2
+         UNION ALL
+        SELECT msgid FROM StickerLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $2))
+ GROUP BY userid, reaction
+ ORDER BY userid, uses DESC, reaction
@@ -0,0 +1,10 @@
+SELECT userid, COUNT(*) AS given
+  FROM ReactionLogs
+ WHERE chatid = $1
+   AND ($3::BIGINT IS NULL OR msgid IN (
+        SELECT msgid FROM MessageLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $3
+         UNION ALL
+        SELECT msgid FROM StickerLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $3))
+ GROUP BY userid
+ ORDER BY given DESC
+ LIMIT $2
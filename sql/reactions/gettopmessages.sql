@@ -0,0 +1,23 @@
+-- Reaction counts cover anonymous reactions too, but aren't sent for every chat
+WITH totals AS (
+  SELECT msgid, MAX(total)::BIGINT AS total
+    FROM (SELECT msgid, COUNT(*) AS total
+            FROM ReactionLogs
+           WHERE chatid = $1
+           GROUP BY msgid
+           UNION ALL
+          SELECT msgid, SUM(count) AS total
+            FROM ReactionCounts
+           WHERE chatid = $1
+           GROUP BY msgid) AS combined
+   GROUP BY msgid
+)
+SELECT totals.msgid, totals.total, m.userid, m.message
+  FROM totals
+  LEFT JOIN MessageLogs m ON m.chatid = $1 AND m.msgid = totals.msgid
+ WHERE ($3::BIGINT IS NULL OR totals.msgid IN (
+       SELECT msgid FROM MessageLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $3
+        UNION ALL
+       SELECT msgid FROM StickerLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $3))
+ ORDER BY totals.total DESC, totals.msgid DESC
+ LIMIT $2
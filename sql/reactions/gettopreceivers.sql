@@ -0,0 +1,18 @@
+WITH authors AS (
+  SELECT msgid, userid FROM MessageLogs WHERE chatid = $1
+   UNION ALL
+  SELECT msgid, userid FROM StickerLogs WHERE chatid = $1
+   UNION ALL
+  SELECT msgid, userid FROM PhotoLogs WHERE chatid = $1
+)
+SELECT authors.userid, COUNT(*) AS received
+  FROM ReactionLogs r
+  JOIN authors ON authors.msgid = r.msgid
+ WHERE r.chatid = $1
+   AND ($3::BIGINT IS NULL OR r.msgid IN (
+        SELECT msgid FROM MessageLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $3
+         UNION ALL
+        SELECT msgid FROM StickerLogs WHERE chatid = $1 AND COALESCE(threadid, 1) = $3))
+ GROUP BY authors.userid
+ ORDER BY received DESC
+ LIMIT $2
@@ -0,0 +1,2 @@
+DELETE FROM ReactionLogs
+ WHERE chatid = $1 AND msgid = $2 AND userid = $3 AND reaction = $4
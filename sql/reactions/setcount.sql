@@ -0,0 +1,2 @@
+INSERT INTO ReactionCounts(chatid, msgid, reaction, count)
+VALUES($1, $2, $3, $4)
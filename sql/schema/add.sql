@@ -0,0 +1,2 @@
+INSERT INTO schema_migrations(version, name, checksum, applied)
+VALUES ($1, $2, $3, $4)
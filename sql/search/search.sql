@@ -0,0 +1,11 @@
+SELECT userid, msgid, instant,
+       ts_headline('simple', message, query, $7) AS snippet,
+       COUNT(*) OVER () AS total
+  FROM MessageLogs, websearch_to_tsquery('simple', $2) AS query
+ WHERE chatid = $1
+   AND textsearch @@ query
+   AND ($3::BIGINT IS NULL OR userid = $3)
+   AND instant > $4
+   AND ($8::BIGINT IS NULL OR COALESCE(threadid, 1) = $8)
+ ORDER BY ts_rank(textsearch, query) DESC, instant DESC
+ LIMIT $5 OFFSET $6
@@ -0,0 +1,17 @@
+-- Ends season $2 of chat $1 which started at $4 by archiving its standings at $3, and starts
+-- the next one. The season may not have a row yet if it's the first one.
+WITH ended AS (
+  INSERT INTO DisasterSeasons(chatid, season, started, ended)
+  VALUES ($1, $2, $4, $3)
+      ON CONFLICT(chatid, season) DO
+  UPDATE SET ended = $3
+), archived AS (
+  INSERT INTO DisasterStandings(chatid, season, userid, category, points)
+  SELECT chatid, $2, userid, category, points
+    FROM DisasterStatus
+   WHERE chatid = $1 AND points <> 0
+), cleared AS (
+  DELETE FROM DisasterStatus WHERE chatid = $1
+)
+INSERT INTO DisasterSeasons(chatid, season, started)
+VALUES ($1, $2 + 1, $3)
@@ -0,0 +1,3 @@
+SELECT started, ended
+  FROM DisasterSeasons
+ WHERE chatid = $1 AND season = $2
@@ -0,0 +1,8 @@
+SELECT userid, SUM(points)::BIGINT AS total
+  FROM (SELECT userid, points FROM DisasterStandings WHERE chatid = $1 AND category = $3
+        UNION ALL
+        SELECT userid, points FROM DisasterStatus WHERE chatid = $1 AND category = $3) p
+ GROUP BY userid
+HAVING SUM(points) <> 0
+ ORDER BY total DESC
+ LIMIT $2
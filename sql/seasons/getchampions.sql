@@ -0,0 +1,10 @@
+-- Whoever had the most points of category $2 in every ended season, which can be several users
+SELECT season, userid, points
+  FROM DisasterStandings s
+ WHERE chatid = $1
+   AND category = $2
+   AND points > 0
+   AND points = (SELECT MAX(points)
+                   FROM DisasterStandings
+                  WHERE chatid = $1 AND season = s.season AND category = $2)
+ ORDER BY season, userid
@@ -0,0 +1,3 @@
+SELECT season, started
+  FROM DisasterSeasons
+ WHERE chatid = $1 AND ended IS NULL
@@ -0,0 +1,6 @@
+-- Chats whose seasons last a month, with when their current season started if it has a row yet
+-- and when they turned on monthly seasons
+SELECT s.chatid, d.started, s.changed
+  FROM ChatSettings s
+  LEFT JOIN DisasterSeasons d ON d.chatid = s.chatid AND d.ended IS NULL
+ WHERE s.name = 'seasons' AND s.value = 'monthly'
@@ -0,0 +1,4 @@
+SELECT points, userid, category
+  FROM DisasterStandings
+ WHERE chatid = $1 AND season = $2 AND ($3::TEXT IS NULL OR category = $3) AND points <> 0
+ ORDER BY category, points DESC
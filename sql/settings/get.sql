@@ -0,0 +1,3 @@
+SELECT value
+  FROM ChatSettings
+ WHERE chatid = $1 AND name = $2
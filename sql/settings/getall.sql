@@ -0,0 +1,3 @@
+SELECT name, value
+  FROM ChatSettings
+ WHERE chatid = $1
@@ -0,0 +1,2 @@
+DELETE FROM ChatSettings
+ WHERE chatid = $1 AND name = $2
@@ -0,0 +1,6 @@
+-- Setting a value again doesn't count as changing it
+INSERT INTO ChatSettings(chatid, name, value, changed)
+VALUES($1, $2, $3, $4)
+ON CONFLICT(chatid, name) DO
+UPDATE SET value = $3,
+           changed = CASE WHEN ChatSettings.value = $3 THEN ChatSettings.changed ELSE $4 END
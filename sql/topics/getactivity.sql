@@ -0,0 +1,7 @@
+SELECT COALESCE(m.threadid, 1) AS topic, t.name,
+       COUNT(*) AS messages, COUNT(DISTINCT m.userid) AS users, MAX(m.instant) AS lastactive
+  FROM MessageLogs m
+  LEFT JOIN ForumTopics t ON t.chatid = m.chatid AND t.threadid = COALESCE(m.threadid, 1)
+ WHERE m.chatid = $1
+ GROUP BY COALESCE(m.threadid, 1), t.name
+ ORDER BY messages DESC
@@ -0,0 +1,4 @@
+SELECT threadid
+  FROM ForumTopics
+ WHERE chatid = $1 AND LOWER(name) = LOWER($2)
+ ORDER BY threadid DESC
@@ -0,0 +1,4 @@
+INSERT INTO ForumTopics(chatid, threadid, name)
+VALUES($1, $2, $3)
+    ON CONFLICT(chatid, threadid) DO
+UPDATE SET name = EXCLUDED.name
@@ -0,0 +1,8 @@
+SELECT ngram, SUM(uses)::BIGINT AS total
+  FROM NGramCounts
+ WHERE chatid = $1 AND n = 1
+   AND ($2::BIGINT IS NULL OR threadid = $2)
+   AND NOT ngram = ANY($3)
+ GROUP BY ngram
+ ORDER BY total DESC, ngram
+ LIMIT $4
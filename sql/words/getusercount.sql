@@ -0,0 +1,4 @@
+SELECT COUNT(DISTINCT userid)
+  FROM NGramCounts
+ WHERE chatid = $1 AND n = 1
+   AND ($2::BIGINT IS NULL OR threadid = $2)
@@ -0,0 +1,17 @@
+-- Every word of a user along with how many users have used it
+WITH scope AS (
+  SELECT userid, ngram, uses
+    FROM NGramCounts
+   WHERE chatid = $1 AND n = 1
+     AND ($3::BIGINT IS NULL OR threadid = $3)
+     AND NOT ngram = ANY($4)
+), words AS (
+  SELECT ngram, SUM(uses)::BIGINT AS uses
+    FROM scope
+   WHERE userid = $2
+   GROUP BY ngram
+)
+SELECT w.ngram, w.uses, COUNT(DISTINCT s.userid) AS users
+  FROM words w
+  JOIN scope s ON s.ngram = w.ngram
+ GROUP BY w.ngram, w.uses
@@ -0,0 +1,4 @@
+SELECT COALESCE(SUM(uses), 0)::BIGINT
+  FROM NGramCounts
+ WHERE chatid = $1 AND n = 1 AND ngram = $2
+   AND ($3::BIGINT IS NULL OR threadid = $3)
@@ -1,23 +1,37 @@
 use crate::{
-    include_sql, params,
-    telegram::{
-        chat::{Chat, ChatType},
-        message::Message,
-        Telegram,
-    },
-    util::{align_text_after, get_user, get_user_id},
+    include_sql, params, render,
+    telegram::{chat::ChatType, message::Message, ChatTarget, Telegram},
+    tokenizer::{fold_case, tokenize},
+    util::{align_text_after, get_user, get_users, left_users, topic_scope, Arguments},
     Context,
 };
-use cairo::Format;
 use chrono::{prelude::*, Utc};
 use markov::Chain;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
 use tokio::task;
 use unicode_segmentation::UnicodeSegmentation;
 
+mod categories;
 pub mod disaster;
+mod disasterchart;
+pub mod disastervote;
+mod emoji;
+pub mod links;
+mod names;
+mod packs;
+mod phrases;
+pub mod pickuser;
+mod reactions;
+pub mod search;
+pub mod seasons;
+pub mod settings;
 mod stickerlog;
+mod topics;
+mod words;
 
 pub use stickerlog::stickerlog;
 
@@ -26,11 +40,6 @@ pub async fn log_command(command: &str, context: &Context, message: &Message) {
     let time: DateTime<Local> = Utc.timestamp_opt(message.date, 0).unwrap().into();
     let mut conn = context.db_pool.get().await.unwrap();
     let tx = conn.transaction().await.unwrap();
-    //Register function
-    tx.execute(include_sql!("getcommandidfunction.sql"), &[])
-        .await
-        .unwrap();
-
     let command_id: Result<i64, ()> = tx
         .query_one(include_sql!("getcommandid.sql"), params![command])
         .await
@@ -65,102 +74,158 @@ fn get_command<'a>(input: &'a str, botname: &str) -> Option<&'a str> {
     }
 }
 
-async fn leaderboards<'a>(
-    chatid: i64,
+//Options of the statistics commands which take no other arguments
+struct StatsOptions {
+    topic: Option<i64>,
+    //Users who left the chat
+    left: HashSet<i64>,
+    //Leave out users who left instead of marking them
+    hide_left: bool,
+}
+
+impl StatsOptions {
+    //The error is meant to be shown to the user
+    async fn parse(
+        msg: &Message,
+        args: &[String],
+        usage: &str,
+        context: &Context,
+    ) -> Result<Self, String> {
+        let arguments = Arguments::parse(&args[1..]);
+        if let Some(flag) = arguments.unknown_flag(&["topic", "hideleft"]) {
+            return Err(format!("Unknown option --{}\n{}", flag, usage));
+        }
+        if !arguments.positional.is_empty() {
+            return Err(usage.to_string());
+        }
+        let topic = topic_scope(msg, &arguments, &context.db_pool).await?;
+        let left = left_users(msg.chat.id, &context.db_pool)
+            .await
+            .unwrap_or_else(|e| {
+                error!("{}", e);
+                HashSet::new()
+            });
+        Ok(Self {
+            topic,
+            left,
+            hide_left: arguments.flag("hideleft").is_some(),
+        })
+    }
+
+    fn is_shown(&self, userid: i64) -> bool {
+        !(self.hide_left && self.left.contains(&userid))
+    }
+}
+
+async fn leaderboards(
+    msg: &Message,
+    args: &[String],
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let usage = "Usage: /leaderboards [--topic [<name>]] [--hideleft]";
+    let options = match StatsOptions::parse(msg, args, usage, context).await {
+        Ok(o) => o,
+        Err(e) => {
+            return telegram
+                .send_message_silent(msg, e)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending leaderboards error message: {}", e))
+        }
+    };
+
     let conn = context.db_pool.get().await.unwrap();
     let messages = conn
-        .query(include_sql!("getmessages.sql"), params![chatid])
+        .query(
+            include_sql!("getmessages.sql"),
+            params![chatid, options.topic],
+        )
         .await
         .map_err(|e| format!("getting messages: {:?}", e))?
         .into_iter()
         .map(|row| (row.get(0), row.get(1)))
+        .filter(|(user, _)| options.is_shown(*user))
         .collect::<Vec<(i64, i64)>>();
 
     if messages.is_empty() {
         return telegram
-            .send_message_silent(chatid, "Error: No logged messages in this chat".into())
+            .send_message_silent(msg, "Error: No logged messages in this chat".into())
             .await
             .map(|_| ())
             .map_err(|e| format!("sending no messages exist message: {}", e));
     }
 
     let (total_msgs, since): (i64, i64) = conn
-        .query_one(include_sql!("getmessagesdata.sql"), params![chatid])
+        .query_one(
+            include_sql!("getmessagesdata.sql"),
+            params![chatid, options.topic],
+        )
         .await
         .map(|row| (row.get(0), row.get(1)))
         .map_err(|e| format!("getting message data: {:?}", e))?;
 
+    let edits = conn
+        .query(
+            include_sql!("geteditpercentage.sql"),
+            params![chatid, options.topic],
+        )
+        .await
+        .map_err(|e| format!("getting edit percentage: {:?}", e))?
+        .into_iter()
+        .map(|row| {
+            (
+                row.get(0),
+                row.get::<usize, f64>(1),
+                row.get::<usize, i64>(2),
+            )
+        })
+        .filter(|(user, _, _)| options.is_shown(*user))
+        .collect::<Vec<(i64, f64, i64)>>();
+
     let since = chrono::Local.timestamp_opt(since, 0).unwrap();
     let mut redis = context.redis_pool.get().await;
+    let ids: Vec<i64> = messages
+        .iter()
+        .map(|m| m.0)
+        .chain(edits.iter().map(|e| e.0))
+        .collect();
+    let names = get_users(chatid, &ids, telegram, context, &mut redis).await;
 
     //Message counts
     let mut reply = format!("```\n{} messages since {}\n", total_msgs, since);
     let mut messages = messages.into_iter();
     if let Some((user, count)) = messages.next() {
-        //store appendage here because otherwise this future doesn't implement Sync for
-        //whatever reason
-        let appendage = format!(
+        reply += &format!(
             "{} is the most annoying having sent {} messages!\n",
-            get_user(chatid, user, telegram, &context.config, &mut redis).await,
-            count
+            names[&user], count
         );
-
-        reply += &appendage;
     }
 
     //Stores text to be aligned later
     let mut table = String::new();
-    for m in messages {
-        let appendage = format!(
-            "{}: {} messages\n",
-            get_user(chatid, m.0, telegram, &context.config, &mut redis).await,
-            m.1
-        );
-        table += &appendage;
+    for (user, count) in messages {
+        table += &format!("{}: {} messages\n", names[&user], count);
     }
     reply += &align_text_after(':', table);
     reply += "\n";
 
     // Edits
-    let mut edits = conn
-        .query(include_sql!("geteditpercentage.sql"), params![chatid])
-        .await
-        .map_err(|e| format!("getting edit percentage: {:?}", e))?
-        .into_iter()
-        .map(|row| {
-            (
-                row.get(0),
-                row.get::<usize, f64>(1),
-                row.get::<usize, i64>(2),
-            )
-        });
-
+    let mut edits = edits.into_iter();
     if let Some((user, percentage, count)) = edits.next() {
-        let appendage = format!(
+        reply += &format!(
             "{} is the biggest disaster, having edited {:.2}% of their messages({} edits total)!\n",
-            get_user(chatid, user, telegram, &context.config, &mut redis).await,
-            percentage,
-            count
+            names[&user], percentage, count
         );
-        reply += &appendage;
     }
     let mut table = String::new();
     for (user, percentage, count) in edits {
-        let appendage = format!(
-            "{}: {:.2}% ({})\n",
-            get_user(chatid, user, telegram, &context.config, &mut redis).await,
-            percentage,
-            count
-        );
-
-        table += &appendage;
+        table += &format!("{}: {:.2}% ({})\n", names[&user], percentage, count);
     }
     reply += &align_text_after(':', table);
     telegram
-        .send_message_silently_with_markdown(chatid, format!("{}```", reply))
+        .send_message_silently_with_markdown(msg, format!("{}```", reply))
         .await
         .map(|_| ())
         .map_err(|e| format!("sending leaderboards message: {}", e))
@@ -190,29 +255,6 @@ fn merge_messages(messages: &[MessageData], mut index: usize) -> (usize, String)
     (index, output)
 }
 
-fn convert_msgs_to_lowercase(messages: &mut [MessageData]) {
-    for m in messages.iter_mut() {
-        if !m.message.contains("https://") && !m.message.contains("http://") {
-            m.message.make_ascii_lowercase();
-            continue;
-        }
-
-        let mut output = String::with_capacity(m.message.len());
-        for (index, word) in m.message.split_whitespace().enumerate() {
-            if index > 0 {
-                output += " ";
-            }
-            // Try to preserve links
-            if word.starts_with("http://") || word.starts_with("https://") {
-                output += word;
-            } else {
-                output += &word.to_ascii_lowercase();
-            }
-        }
-        m.message = output;
-    }
-}
-
 async fn get_simulate_chat_chain(
     chatid: i64,
     order: usize,
@@ -233,8 +275,11 @@ async fn get_simulate_chat_chain(
             //Create a new chain
             let mut chain = Chain::of_order(order);
             let conn = context.db_pool.get().await.unwrap();
-            let mut messages = conn
-                .query(include_sql!("getmessagetext.sql"), params![chatid])
+            let messages = conn
+                .query(
+                    include_sql!("getmessagetext.sql"),
+                    params![chatid, None::<i64>],
+                )
                 .await
                 .map_err(|e| format!("getting user message text: {:?}", e))?
                 .into_iter()
@@ -249,12 +294,10 @@ async fn get_simulate_chat_chain(
                 return Ok(None);
             }
 
-            convert_msgs_to_lowercase(&mut messages);
-
             let mut i = 0;
             while i < messages.len() {
                 let (index, merged) = merge_messages(&messages, i);
-                chain.feed_str(&merged);
+                chain.feed(tokenize(&merged));
                 i = index + 1;
             }
             //Cache for later
@@ -272,16 +315,16 @@ async fn get_simulate_chat_chain(
 //This function is almost identical to simulate except it creates a chain with messages from every user and not just one
 async fn simulate_chat(
     order: usize,
-    chat: &Chat,
+    msg: &Message,
     telegram: &Telegram,
     context: &Context,
     starting_token: Option<&str>,
 ) -> Result<(), String> {
-    let chain = if let Some(s) = get_simulate_chat_chain(chat.id, order, context).await? {
+    let chain = if let Some(s) = get_simulate_chat_chain(msg.chat.id, order, context).await? {
         s
     } else {
         return telegram
-            .send_message_silent(chat.id, "No logged messages in this chat".to_string())
+            .send_message_silent(msg, "No logged messages in this chat".to_string())
             .await
             .map(|_| ())
             .map_err(|e| format!("sending no logged messages message: {}", e));
@@ -289,14 +332,14 @@ async fn simulate_chat(
 
     let mut output = format!(
         "Simulated {}: {}",
-        chat,
+        msg.chat,
         generate_string_with_minimum_words(
             chain,
             starting_token,
             context.config.markov.min_words,
             context.config.markov.max_attempts,
             telegram,
-            chat.id
+            msg.into()
         )
         .await
     );
@@ -305,7 +348,7 @@ async fn simulate_chat(
     output.truncate(4000);
 
     telegram
-        .send_message_silent(chat.id, output)
+        .send_message_silent(msg, output)
         .await
         .map(|_| ())
         .map_err(|e| format!("sending simulated string: {}", e))
@@ -318,11 +361,11 @@ async fn generate_string_with_minimum_words(
     max_attempts: usize,
     //For sending an error message
     telegram: &Telegram,
-    chat_id: i64,
+    chat: ChatTarget,
 ) -> String {
     for i in 0..max_attempts {
         let out = if let Some(s) = starting_token {
-            let s = s.to_lowercase();
+            let s = fold_case(s);
             chain.generate_str_from_token(&s)
         } else {
             chain.generate_str()
@@ -341,7 +384,7 @@ async fn generate_string_with_minimum_words(
     if let Some(s) = starting_token {
         if let Err(e) = telegram
             .send_message_silent(
-                chat_id,
+                chat,
                 format!(
                     "Failed to generate a long enough string from token \"{}\"",
                     s
@@ -361,7 +404,7 @@ async fn generate_string_with_exact_words(
     max_attempts: usize,
     //For sending an error message
     telegram: &Telegram,
-    chat_id: i64,
+    chat: ChatTarget,
 ) -> Option<String> {
     for i in 0..max_attempts {
         let generated = chain.generate_str();
@@ -377,7 +420,7 @@ async fn generate_string_with_exact_words(
     }
     warn!("Used up all simulation attempts!");
     if let Err(e) = telegram
-        .send_message_silent(chat_id, "Failed to generate message".to_string())
+        .send_message_silent(chat, "Failed to generate message".to_string())
         .await
     {
         error!("Failed to send generation error message: {:?}", e);
@@ -386,15 +429,15 @@ async fn generate_string_with_exact_words(
 }
 
 async fn haiku(
-    chat: &Chat,
+    msg: &Message,
     order: usize,
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
-    let chain = get_simulate_chat_chain(chat.id, order, context).await?;
+    let chain = get_simulate_chat_chain(msg.chat.id, order, context).await?;
     if chain.is_none() {
         return telegram
-            .send_message_silent(chat.id, "No messages logged in this chat".into())
+            .send_message_silent(msg, "No messages logged in this chat".into())
             .await
             .map(|_| ())
             .map_err(|e| format!("Replying with error: {}", e));
@@ -402,14 +445,14 @@ async fn haiku(
 
     let chain = chain.unwrap();
 
-    let mut poem = format!("A haiku, written by {}:\n```\n", chat);
+    let mut poem = format!("A haiku, written by {}:\n```\n", msg.chat);
     for size in [5, 7, 5] {
         match generate_string_with_exact_words(
             &chain,
             size,
             context.config.markov.max_attempts,
             telegram,
-            chat.id,
+            msg.into(),
         )
         .await
         {
@@ -423,7 +466,7 @@ async fn haiku(
     poem += "```";
 
     telegram
-        .send_message_silently_with_markdown(chat.id, poem)
+        .send_message_silently_with_markdown(msg, poem)
         .await
         .map(|_| ())
 }
@@ -450,8 +493,11 @@ async fn get_simulate_chain(
             let mut chain = Chain::of_order(order);
             let conn = context.db_pool.get().await.unwrap();
 
-            let mut messages = conn
-                .query(include_sql!("getmessagetext.sql"), params![chatid])
+            let messages = conn
+                .query(
+                    include_sql!("getmessagetext.sql"),
+                    params![chatid, None::<i64>],
+                )
                 .await
                 .map_err(|e| format!("getting user message text: {:?}", e))?
                 .into_iter()
@@ -466,13 +512,11 @@ async fn get_simulate_chain(
                 return Ok(None);
             }
 
-            convert_msgs_to_lowercase(&mut messages);
-
             let mut i = 0;
             while i < messages.len() {
                 if messages[i].userid == userid {
                     let (index, merged) = merge_messages(&messages, i);
-                    chain.feed_str(&merged);
+                    chain.feed(tokenize(&merged));
                     i = index + 1;
                 } else {
                     i += 1;
@@ -492,13 +536,14 @@ async fn get_simulate_chain(
 
 pub async fn simulate(
     userid: i64,
-    chatid: i64,
+    chat: ChatTarget,
     order: usize,
     command_message_id: i64,
     telegram: &Telegram,
     context: &Context,
     starting_token: Option<&str>,
 ) -> Result<(), String> {
+    let chatid = chat.chat_id;
     let mut redis = context.redis_pool.get().await;
     let chain =
         if let Some(s) = get_simulate_chain(userid, chatid, order, context, &mut redis).await? {
@@ -507,7 +552,7 @@ pub async fn simulate(
             return telegram
                 .reply_and_close_keyboard(
                     command_message_id,
-                    chatid,
+                    chat,
                     "Error: No logged messages in this chat".into(),
                 )
                 .await
@@ -517,14 +562,14 @@ pub async fn simulate(
 
     let mut output = format!(
         "{}<s>: {}",
-        get_user(chatid, userid, telegram, &context.config, &mut redis).await,
+        get_user(chatid, userid, telegram, context, &mut redis).await,
         generate_string_with_minimum_words(
             chain,
             starting_token,
             context.config.markov.min_words,
             context.config.markov.max_attempts,
             telegram,
-            chatid
+            chat
         )
         .await
     );
@@ -533,7 +578,7 @@ pub async fn simulate(
     output.truncate(4000);
 
     telegram
-        .reply_and_close_keyboard(command_message_id, chatid, output)
+        .reply_and_close_keyboard(command_message_id, chat, output)
         .await
         .map(|_| ())
         .map_err(|e| format!("sending simulated string: {}", e))
@@ -541,11 +586,12 @@ pub async fn simulate(
 
 pub async fn quote(
     userid: i64,
-    chatid: i64,
+    chat: ChatTarget,
     command_message_id: i64,
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
+    let chatid = chat.chat_id;
     let conn = context.db_pool.get().await.unwrap();
     let (message, timestamp): (String, i64) = conn
         .query_one(
@@ -566,11 +612,11 @@ pub async fn quote(
     telegram
         .reply_and_close_keyboard(
             command_message_id,
-            chatid,
+            chat,
             format!(
                 "\"{}\" -- {}, {}",
                 message,
-                get_user(chatid, userid, telegram, &context.config, &mut redis).await,
+                get_user(chatid, userid, telegram, context, &mut redis).await,
                 date
             ),
         )
@@ -579,84 +625,52 @@ pub async fn quote(
         .map_err(|e| format!("sending qoute: {}", e))
 }
 
-fn render_wordcounts_to_surface(
-    words: &[(String, i64)],
-) -> Result<cairo::ImageSurface, cairo::Error> {
-    //Initialize image with some constants
-    let padding = 20.0;
-    let thickness = 25.0;
-    let y_shift = 30.0;
-    let height_unit = 800.0 / f64::from(words[0].1 as i32); //Limit height of bar to 800 and have each other bar be a representation of that
-    let width = (padding + thickness) * words.len() as f64 + 50.0;
-    let height = (f64::from(words[0].1 as i32) * height_unit + 70.0 + y_shift).ceil();
-    let font_size = 15.0;
-
-    let surface = cairo::ImageSurface::create(Format::ARgb32, width as i32, height as i32)?;
-    let cairo = cairo::Context::new(&surface)?;
-    cairo.scale(1.0, 1.0);
-
-    #[allow(clippy::unnecessary_cast)]
-    cairo.set_source_rgba(
-        0x2E as f64 / 0xFF as f64,
-        0x2E as f64 / 0xFF as f64,
-        0x2E as f64 / 0xFF as f64,
-        1.0,
-    );
-    cairo.rectangle(0.0, 0.0, width, height);
-    cairo.fill()?;
-
-    for (index, (word, uses)) in words.iter().enumerate() {
-        cairo.set_source_rgba(0.5, 0.5, 1.0, 1.0);
-        let x_pos = (thickness + padding) * index as f64 + padding;
-        let bar_height = f64::from(*uses as i32) * height_unit;
-        cairo.rectangle(x_pos, padding + y_shift, thickness, bar_height);
-        cairo.fill()?;
-
-        //Render the text
-        cairo.select_font_face("Hack", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
-        cairo.set_font_size(font_size);
-        cairo.set_source_rgba(1.0, 1.0, 1.0, 1.0);
-
-        //Number
-        let number_y_position = bar_height + 5.0 + y_shift + padding + font_size;
-        let number_text = uses.to_string();
-        // let extends = cairo.text_extents(&number_text);
-        cairo.move_to(x_pos, number_y_position);
-        cairo.show_text(&number_text)?;
-
-        //Stagger the text in order make the words more readable
-        let text_y_position = font_size
-            + if index % 2 == 0 {
-                y_shift
-            } else {
-                y_shift / 2.0
-            };
-        cairo.move_to(x_pos, text_y_position);
-        cairo.show_text(word)?;
+const WORDCOUNT_USAGE: &str = "Usage: /wordcount [<word>] [--topic [<name>]]";
+
+async fn wordcount(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending wordcount error message: {}", e))
+        };
     }
-    Ok(surface)
-}
 
-fn render_wordcounts(words: &[(String, i64)]) -> Result<Vec<u8>, String> {
-    let surface =
-        render_wordcounts_to_surface(words).map_err(|e| format!("Cairo error: {:?}", e))?;
-    let mut rendered_image = Vec::new();
-    surface
-        .write_to_png(&mut rendered_image)
-        .map_err(|e| format!("Writing as PNG failed: {:?}", e))?;
-    Ok(rendered_image)
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, WORDCOUNT_USAGE));
+    }
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+    match arguments.positional.as_slice() {
+        [] => wordcount_graph(msg, topic, telegram, context).await,
+        [word] => count_word(word, msg, topic, telegram, context).await,
+        _ => reply!("Invalid number of arguments".to_string()),
+    }
 }
 
 async fn wordcount_graph(
     command_message: &Message,
+    topic: Option<i64>,
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
+    let chatid = command_message.chat.id;
+    let stop_words = settings::stop_words(chatid, context).await?.words();
     let conn = context.db_pool.get().await.unwrap();
     let results = conn
         .query(
-            include_sql!("getwordcounts.sql"),
-            params![command_message.chat.id, 60i64],
+            include_sql!("words/gettop.sql"),
+            params![chatid, topic, stop_words, 60i64],
         )
         .await
         .map_err(|e| format!("getting word counts: {:?}", e))?
@@ -668,7 +682,7 @@ async fn wordcount_graph(
         return telegram
             .reply_to(
                 command_message.id,
-                command_message.chat.id,
+                command_message,
                 "There are no logged messages in this chat!".into(),
             )
             .await
@@ -677,30 +691,44 @@ async fn wordcount_graph(
     }
     //Perform this in a block such that the cairo context gets dropped before anything else.
     //Without this, this future won't be Sync.
-    let image = task::block_in_place(|| render_wordcounts(&results))?;
+    let image = task::block_in_place(|| render::bar_chart(&results, "Hack"))?;
     telegram
-        .send_png_lossless(command_message.chat.id, image, None, true)
+        .send_png_lossless(command_message, image, None, true)
         .await
         .map(|_| ())
         .map_err(|_| "sending rendered image".to_string())
 }
 
-async fn wordcount(
+async fn count_word(
     word: &str,
-    chat: &Chat,
+    msg: &Message,
+    topic: Option<i64>,
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
+    let mut tokens = tokenize(word);
+    if tokens.len() != 1 {
+        return telegram
+            .send_message_silent(msg, format!("'{}' is not a single word", word))
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending not a word message: {}", e));
+    }
+    let token = tokens.remove(0);
+
     let conn = context.db_pool.get().await.unwrap();
     let usages: i64 = conn
-        .query_one(include_sql!("getwordusage.sql"), params![word, chat.id])
+        .query_one(
+            include_sql!("words/getuses.sql"),
+            params![msg.chat.id, token, topic],
+        )
         .await
-        .map(|row| row.get(1))
-        .unwrap_or(0);
+        .map(|row| row.get(0))
+        .map_err(|e| format!("getting word usage: {:?}", e))?;
 
     telegram
         .send_message_silent(
-            chat.id,
+            msg,
             format!("I have seen the word '{}' {} time(s).", word, usages),
         )
         .await
@@ -708,20 +736,42 @@ async fn wordcount(
         .map_err(|e| format!("sending word count message: {}", e))
 }
 
-async fn charcount(chatid: i64, telegram: &Telegram, context: &Context) -> Result<(), String> {
+async fn charcount(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let usage = "Usage: /charcount [--topic [<name>]] [--hideleft]";
+    let options = match StatsOptions::parse(msg, args, usage, context).await {
+        Ok(o) => o,
+        Err(e) => {
+            return telegram
+                .send_message_silent(msg, e)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending charcount error message: {}", e))
+        }
+    };
+
     let conn = context.db_pool.get().await.unwrap();
     let messages: Vec<(i64, String)> = conn
-        .query(include_sql!("getmessagebyuser.sql"), params![chatid])
+        .query(
+            include_sql!("getmessagebyuser.sql"),
+            params![chatid, options.topic],
+        )
         .await
         .map_err(|e| format!("getting messages and userid: {:?}", e))?
         .into_iter()
         .map(|row| (row.get(0), row.get(1)))
+        .filter(|(user, _)| options.is_shown(*user))
         .collect();
 
     if messages.is_empty() {
         return telegram
             .send_message_silent(
-                chatid,
+                msg,
                 "There are no messages logged in this chat!".to_string(),
             )
             .await
@@ -747,12 +797,16 @@ async fn charcount(chatid: i64, telegram: &Telegram, context: &Context) -> Resul
     let first = sorted.next().unwrap();
 
     let mut redis = context.redis_pool.get().await;
+    let ids: Vec<i64> = std::iter::once(first.0)
+        .chain(sorted.as_slice().iter().map(|x| x.0))
+        .collect();
+    let names = get_users(chatid, &ids, telegram, context, &mut redis).await;
 
     macro_rules! get_user_msgcount {
         ($userid:expr) => {
             conn.query_one(
                 include_sql!("getusermessagecount.sql"),
-                params![chatid, $userid],
+                params![chatid, $userid, options.topic],
             )
             .await
             .map(|row| row.get(0))
@@ -763,9 +817,7 @@ async fn charcount(chatid: i64, telegram: &Telegram, context: &Context) -> Resul
     let msgcount: i64 = get_user_msgcount!(first.0);
 
     let mut averages = Vec::new();
-    let first_user = get_user(chatid, first.0, telegram, &context.config, &mut redis)
-        .await
-        .to_string();
+    let first_user = names[&first.0].to_string();
     let mut output = format!(
         "```\n{} has flooded the most with {} characters sent in {} messages!\n",
         first_user, first.1, msgcount
@@ -776,9 +828,7 @@ async fn charcount(chatid: i64, telegram: &Telegram, context: &Context) -> Resul
     let mut table = String::new();
     for (userid, chars) in sorted {
         let msgcount: i64 = get_user_msgcount!(userid);
-        let user = get_user(chatid, userid, telegram, &context.config, &mut redis)
-            .await
-            .to_string();
+        let user = names[&userid].to_string();
         table += &format!("{}: {} ({})\n", &user, chars, msgcount);
         averages.push((user, chars as f32 / msgcount as f32));
     }
@@ -800,7 +850,7 @@ async fn charcount(chatid: i64, telegram: &Telegram, context: &Context) -> Resul
     output += "```";
 
     telegram
-        .send_message_silently_with_markdown(chatid, output)
+        .send_message_silently_with_markdown(msg, output)
         .await
         .map(|_| ())
         .map_err(|e| format!("sending char count message: {}", e))
@@ -834,6 +884,17 @@ pub enum ReplyAction {
     AddDisasterPoint,
 }
 
+impl ReplyAction {
+    //The command which does the same when given the user right away
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Simulate => "/simulate",
+            Self::Quote => "/quote",
+            Self::AddDisasterPoint => "/disaster",
+        }
+    }
+}
+
 impl fmt::Display for ReplyAction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -867,18 +928,15 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
     macro_rules! with_user {
         ($action:expr, $fun:ident ( _, $( $arg:expr ),* ) ) => {
             if split.len() >= 2 {
-                match get_user_id(msg.chat.id, &split[1], &context.db_pool).await {
-                    Some(u) => {
+                match pickuser::resolve_user(msg, &split[1], 1, telegram, context).await {
+                    Ok(Some(u)) => {
                         $fun(u, $($arg),*).await
                     }
-                    None => telegram
-                        .send_message_silent(
-                            msg.chat.id,
-                            format!("I haven't seen {} yet", &split[1]),
-                        )
-                        .await
-                        .map(|_| ())
-                        .map_err(|e| format!("sending invalid user message: {}", e)),
+                    Ok(None) => {
+                        should_log = false;
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
                 }
             } else {
                 should_log = false;
@@ -916,7 +974,7 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
                 let request_message = telegram
                     .reply_with_markup(
                         msg.id,
-                        msg.chat.id,
+                        msg,
                         format!("Please select a user to {}", $action),
                         serde_json::json!({
                             "keyboard": buttons,
@@ -945,16 +1003,16 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
     }
 
     let res = match root {
-        "/leaderboards" => leaderboards(msg.chat.id, telegram, context).await,
+        "/leaderboards" => leaderboards(msg, &split, telegram, context).await,
         "/stickerlog" => stickerlog(msg, &split, telegram, context).await,
         "/quote" => with_user!(
             ReplyAction::Quote,
-            quote(_, msg.chat.id, msg.id, telegram, context)
+            quote(_, msg.into(), msg.id, telegram, context)
         ),
         "/haiku" => match get_order(split.get(1), context) {
-            Ok(order) => haiku(&msg.chat, order, telegram, context).await,
+            Ok(order) => haiku(msg, order, telegram, context).await,
             Err(e) => telegram
-                .send_message_silent(msg.chat.id, e)
+                .send_message_silent(msg, e)
                 .await
                 .map(|_| ())
                 .map_err(|e| format!("sending invalid order string: {}", e)),
@@ -964,7 +1022,7 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
                 if split.len() > 4 {
                     telegram
                         .send_message_silently_with_markdown(
-                            msg.chat.id,
+                            msg,
                             "Extra argument! Usage: `/simulate <username> [<order> [<starting word>]]`".to_string(),
                         )
                         .await
@@ -974,12 +1032,12 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
                     let starting_token = split.get(3).map(|s| s.as_str());
                     with_user!(
                         ReplyAction::Simulate,
-                        simulate(_, msg.chat.id, n, msg.id, telegram, context, starting_token)
+                        simulate(_, msg.into(), n, msg.id, telegram, context, starting_token)
                     )
                 }
             }
             Err(e) => telegram
-                .send_message_silent(msg.chat.id, e)
+                .send_message_silent(msg, e)
                 .await
                 .map(|_| ())
                 .map_err(|e| format!("sending invalid order message: {}", e)),
@@ -989,7 +1047,7 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
                 if split.len() > 3 {
                     telegram
                         .send_message_silently_with_markdown(
-                            msg.chat.id,
+                            msg,
                             "Extra argument! Usage: `/simulatechat [<order>[<starting word>]]`"
                                 .to_string(),
                         )
@@ -998,48 +1056,64 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
                         .map_err(|e| format!("Sending simulation usage string: {}", e))
                 } else {
                     let starting_token = split.get(2).map(|s| s.as_str());
-                    simulate_chat(n, &msg.chat, telegram, context, starting_token).await
+                    simulate_chat(n, msg, telegram, context, starting_token).await
                 }
             }
             Err(e) => telegram
-                .send_message_silent(msg.chat.id, e)
+                .send_message_silent(msg, e)
                 .await
                 .map(|_| ())
                 .map_err(|e| format!("sending invalid order message: {}", e)),
         },
-        "/charcount" => charcount(msg.chat.id, telegram, context).await,
-        "/wordcount" => match split.len() {
-            1 => wordcount_graph(msg, telegram, context).await,
-            2 => wordcount(&split[1], &msg.chat, telegram, context).await,
-            _ => telegram
-                .send_message_silent(msg.chat.id, "Invalid number of arguments".to_string())
-                .await
-                .map(|_| ())
-                .map_err(|e| format!("sending invalid argument message: {}", e)),
-        },
+        "/charcount" => charcount(msg, &split, telegram, context).await,
+        "/wordcount" => wordcount(msg, &split, telegram, context).await,
         "/disaster" => {
-            use disaster::add_point;
+            use disaster::add_disaster_point;
+            //Everything after the user is the reason
+            let reason =
+                Some(split.get(2..).unwrap_or_default().join(" ")).filter(|r| !r.is_empty());
             with_user!(
                 ReplyAction::AddDisasterPoint,
-                add_point(
+                add_disaster_point(
                     _,
                     msg.from.id,
-                    msg.chat.id,
+                    msg.into(),
                     msg.id,
-                    msg.date,
+                    reason,
                     telegram,
                     context
                 )
             )
         }
-        "/disasterpoints" => disaster::show_points(msg.chat.id, telegram, context).await,
+        "/give" => disaster::give(msg, &split, telegram, context).await,
+        "/undisaster" | "/ungive" => disaster::undo_point(msg, &split, telegram, context).await,
+        "/disasterpoints" | "/points" => {
+            disaster::show_points(msg, &split, telegram, context).await
+        }
+        "/disasterchart" => disasterchart::disaster_chart(msg, &split, telegram, context).await,
+        "/pointcategory" => categories::point_category(msg, &split, telegram, context).await,
+        "/newseason" => seasons::new_season(msg, telegram, context).await,
+        "/halloffame" => seasons::hall_of_fame(msg, &split, telegram, context).await,
+        "/emojistats" => emoji::emojistats(msg, &split, telegram, context).await,
+        "/links" => links::links(msg, &split, telegram, context).await,
+        "/packs" => packs::packs(msg, &split, telegram, context).await,
+        "/reactions" => reactions::reactions(msg, &split, telegram, context).await,
+        "/mywords" => words::mywords(msg, &split, telegram, context).await,
+        "/wordtrend" => words::wordtrend(msg, &split, telegram, context).await,
+        "/trending" => words::trending(msg, &split, telegram, context).await,
+        "/search" => search::search(msg, &split, telegram, context).await,
+        "/phrases" => phrases::phrases(msg, &split, telegram, context).await,
+        "/phrase" => phrases::phrase(msg, &split, telegram, context).await,
+        "/settings" => settings::settings(msg, &split, telegram, context).await,
+        "/topics" => topics::topics(msg, telegram, context).await,
+        "/names" => names::names(msg, &split, telegram, context).await,
         _ => {
             should_log = false;
             warn!("No command found for {}", root);
             if let ChatType::Private = msg.chat.kind {
                 //Only nag at the user for wrong command if in a private chat
                 telegram
-                    .send_message_silent(msg.chat.id, "No such command".to_string())
+                    .send_message_silent(msg, "No such command".to_string())
                     .await
                     .map(|_| ())
                     .map_err(|e| format!("sending no such command message: {}", e))
@@ -1054,10 +1128,7 @@ pub async fn handle_command(msg: &Message, msg_text: &str, telegram: &Telegram,
         error!("Command '{}' failed at '{}'", &root[1..], e);
         //If this causes an error something bad must have happened
         let _ = telegram
-            .send_message_silent(
-                msg.chat.id,
-                "Fatal error occurred in command, see bot log".into(),
-            )
+            .send_message_silent(msg, "Fatal error occurred in command, see bot log".into())
             .await;
     }
 
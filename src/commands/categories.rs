@@ -0,0 +1,208 @@
+use super::settings;
+use crate::{
+    include_sql, params,
+    telegram::{message::Message, Telegram},
+    tokenizer::is_emoji,
+    util::Arguments,
+    Context,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+//Disaster points exist in every chat, even without being added as a category
+pub const DEFAULT_CATEGORY: &str = "disaster";
+const MAX_NAME_LENGTH: usize = 20;
+//A year, longer cooldowns make no sense and don't fit a Redis expiry
+const MAX_COOLDOWN: u64 = 365 * 24;
+
+const USAGE: &str = "Usage: /pointcategory [add <name> <emoji> [--cooldown <hours>] [--self] \
+                     [--replies] | remove <name>]";
+
+//A kind of points which can be given to members
+pub struct Category {
+    pub name: String,
+    pub emoji: String,
+    //Hours until the same member can give a point again
+    pub cooldown: u64,
+    //Whether members can give points to themselves
+    pub allow_self: bool,
+    //Whether replying +1 or -1 to a message gives or takes a point
+    pub replies: bool,
+}
+
+fn default_category(context: &Context) -> Category {
+    Category {
+        name: DEFAULT_CATEGORY.to_string(),
+        emoji: "💥".to_string(),
+        cooldown: context.config.disaster.cooldown,
+        allow_self: false,
+        replies: false,
+    }
+}
+
+//Every category of the chat, with disaster points first unless they have been changed
+pub async fn categories(chatid: i64, context: &Context) -> Result<Vec<Category>, String> {
+    let conn = context.db_pool.get().await.unwrap();
+    let mut categories: Vec<Category> = conn
+        .query(include_sql!("points/getcategories.sql"), params![chatid])
+        .await
+        .map_err(|e| format!("getting point categories: {:?}", e))?
+        .into_iter()
+        .map(|row| Category {
+            name: row.get(0),
+            emoji: row.get(1),
+            cooldown: row.get::<_, i64>(2) as u64,
+            allow_self: row.get(3),
+            replies: row.get(4),
+        })
+        .collect();
+    if !categories.iter().any(|c| c.name == DEFAULT_CATEGORY) {
+        categories.insert(0, default_category(context));
+    }
+    Ok(categories)
+}
+
+pub async fn find(chatid: i64, name: &str, context: &Context) -> Result<Option<Category>, String> {
+    let name = name.to_lowercase();
+    Ok(categories(chatid, context)
+        .await?
+        .into_iter()
+        .find(|c| c.name == name))
+}
+
+fn describe(category: &Category) -> String {
+    let mut rules = vec![if category.cooldown == 0 {
+        "no cooldown".to_string()
+    } else {
+        format!("{} hour cooldown", category.cooldown)
+    }];
+    if category.allow_self {
+        rules.push("can be given to yourself".to_string());
+    }
+    if category.replies {
+        rules.push("+1/-1 replies".to_string());
+    }
+    format!(
+        "{} {}: {}\n",
+        category.emoji,
+        category.name,
+        rules.join(", ")
+    )
+}
+
+//Usage: /pointcategory [add <name> <emoji> [--cooldown <hours>] [--self] [--replies] | remove <name>]
+pub async fn point_category(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending point category message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["cooldown", "self", "replies"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, USAGE));
+    }
+    let positional: Vec<&str> = arguments.positional.iter().map(String::as_str).collect();
+    if positional.is_empty() {
+        let mut output = "Point categories in this chat:\n".to_string();
+        for category in categories(chatid, context).await? {
+            output += &describe(&category);
+        }
+        reply!(output);
+    }
+
+    if !settings::is_admin(msg, telegram).await {
+        return telegram
+            .reply_to(
+                msg.id,
+                msg,
+                "Only chat administrators can change point categories".into(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending not an admin message: {}", e));
+    }
+
+    let conn = context.db_pool.get().await.unwrap();
+    match positional.as_slice() {
+        ["add", name, emoji] => {
+            let name = name.to_lowercase();
+            if name.len() > MAX_NAME_LENGTH || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+                reply!(format!(
+                    "Category names can only have up to {} letters and digits",
+                    MAX_NAME_LENGTH
+                ));
+            }
+            let mut graphemes = emoji.graphemes(true);
+            if !matches!((graphemes.next(), graphemes.next()), (Some(g), None) if is_emoji(g)) {
+                reply!(format!("'{}' is not a single emoji", emoji));
+            }
+            let cooldown = match arguments.flag("cooldown") {
+                Some([hours]) => match hours.parse::<u64>() {
+                    Ok(h) if h <= MAX_COOLDOWN => h,
+                    Ok(_) => reply!(format!("Cooldowns can be at most {} hours", MAX_COOLDOWN)),
+                    Err(_) => reply!(format!("'{}' is not a whole number of hours", hours)),
+                },
+                Some(_) => reply!(USAGE.to_string()),
+                None => context.config.disaster.cooldown,
+            };
+            let category = Category {
+                name,
+                emoji: emoji.to_string(),
+                cooldown,
+                allow_self: arguments.flag("self").is_some(),
+                replies: arguments.flag("replies").is_some(),
+            };
+            conn.execute(
+                include_sql!("points/setcategory.sql"),
+                params![
+                    chatid,
+                    category.name,
+                    category.emoji,
+                    category.cooldown as i64,
+                    category.allow_self,
+                    category.replies
+                ],
+            )
+            .await
+            .map_err(|e| format!("setting point category: {:?}", e))?;
+            info!(
+                "[{}] {} set point category {}",
+                msg.chat, msg.from, category.name
+            );
+            reply!(describe(&category));
+        }
+        ["remove", name] => {
+            let name = name.to_lowercase();
+            let removed = conn
+                .execute(
+                    include_sql!("points/removecategory.sql"),
+                    params![chatid, name],
+                )
+                .await
+                .map_err(|e| format!("removing point category: {:?}", e))?;
+            if removed == 0 {
+                reply!(format!("There is no category named {}", name));
+            }
+            info!(
+                "[{}] {} removed point category {}",
+                msg.chat, msg.from, name
+            );
+            //The points themselves are kept in case the category is added again
+            if name == DEFAULT_CATEGORY {
+                reply!("Disaster points are back to how they normally work".to_string());
+            }
+            reply!(format!("Removed the {} category", name));
+        }
+        _ => reply!(USAGE.to_string()),
+    }
+}
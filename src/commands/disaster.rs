@@ -1,29 +1,71 @@
-use crate::{include_sql, params, telegram::Telegram, Context};
+use super::{
+    categories::{self, Category, DEFAULT_CATEGORY},
+    disastervote,
+    pickuser::resolve_user,
+    seasons::{current_season, get_season, Season},
+    settings,
+};
+use crate::{
+    include_sql, params,
+    telegram::{callbackquery::CallbackQuery, message::Message, ChatTarget, Telegram},
+    util::{escape_html, get_user, get_users, page_buttons, Arguments},
+    Context,
+};
 use chrono::prelude::*;
-use darkredis::{Command, CommandList, Value};
-use futures::TryStreamExt;
+use darkredis::Command;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::types::Type;
 
+const HISTORY_PER_PAGE: i64 = 10;
+
+const POINTS_USAGE: &str = "Usage: /points [<category>] [--season <n>]";
+const GIVE_USAGE: &str = "Usage: /give <category> <user> [<reason>]";
+
+fn default_category() -> String {
+    DEFAULT_CATEGORY.to_string()
+}
+
+fn default_amount() -> i64 {
+    1
+}
+
 #[derive(Serialize, Deserialize)]
-struct LastDisaster {
-    from: i64,
-    to: i64,
-    utc: i64,
+pub struct Point {
+    pub giverid: i64,
+    pub receiverid: i64,
+    //The command the point was given with
+    pub messageid: i64,
+    pub reason: Option<String>,
+    #[serde(default = "default_category")]
+    pub category: String,
+    //1 to give a point, -1 to take one
+    #[serde(default = "default_amount")]
+    pub amount: i64,
+}
+
+fn cooldown_key(chatid: i64, giverid: i64, category: &str) -> String {
+    //Disaster cooldowns keep their key from before there were other categories
+    if category == DEFAULT_CATEGORY {
+        format!("tg.disastercooldown.{}.{}", chatid, giverid)
+    } else {
+        format!("tg.disastercooldown.{}.{}.{}", chatid, giverid, category)
+    }
 }
 
 pub async fn add_point(
-    receiverid: i64,
-    giverid: i64,
-    chatid: i64,
-    messageid: i64,
-    timestamp: i64,
+    point: Point,
+    category: &Category,
+    chat: ChatTarget,
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
-    if giverid == receiverid {
+    let chatid = chat.chat_id;
+    if point.giverid == point.receiverid && !category.allow_self {
         return telegram
-            .send_message_silent(chatid, "Cannot give a disaster point to yourself!".into())
+            .send_message_silent(
+                chat,
+                format!("Cannot give a {} point to yourself!", category.name),
+            )
             .await
             .map(|_| ())
             .map_err(|e| format!("sending error message: {}", e));
@@ -31,7 +73,7 @@ pub async fn add_point(
 
     //Check if the user is on cooldown for giving a point
     let mut redis = context.redis_pool.get().await;
-    let cooldown_key = format!("tg.disastercooldown.{}.{}", chatid, giverid);
+    let cooldown_key = cooldown_key(chatid, point.giverid, &category.name);
     let status = redis
         .get(&cooldown_key)
         .await
@@ -48,11 +90,12 @@ pub async fn add_point(
 
         return telegram
             .reply_and_close_keyboard(
-                messageid,
-                chatid,
+                point.messageid,
+                chat,
                 format!(
-                    "You can give a new disaster point in {:0.1} hours",
-                    crate::util::seconds_to_hours(ttl as i32)
+                    "You can give a new {} point in {:0.1} hours",
+                    category.name,
+                    crate::util::seconds_to_hours(ttl as i64)
                 ),
             )
             .await
@@ -60,156 +103,610 @@ pub async fn add_point(
             .map_err(|e| format!("sending error message: {}", e));
     }
 
-    let conn = context.db_pool.get().await.unwrap();
-    conn.execute(
-        include_sql!("disaster/addpoint.sql"),
-        params![chatid, receiverid],
-    )
-    .await
-    .map_err(|e| format!("adding a disaster point: {:?}", e))?;
-
-    //Update last disaster points given list in redis and set cooldown using a pipeline
-    let last_disaster_key = format!("tg.lastdisasterpoints.{}", chatid).into_bytes();
-    let last_disaster = rmp_serde::to_vec(&LastDisaster {
-        from: giverid,
-        to: receiverid,
-        utc: timestamp,
-    })
-    .unwrap();
-    let timeout = (context.config.disaster.cooldown * 3600).to_string();
-
-    let command = CommandList::new("LPUSH")
-        .arg(&last_disaster_key)
-        .arg(&last_disaster)
-        .command("LTRIM") // Store the last 10
-        .arg(&last_disaster_key)
-        .arg(b"0")
-        .arg(b"9")
-        .command("SET")
-        .arg(&cooldown_key)
-        .arg(b"")
-        .arg(b"EX")
-        .arg(&timeout); // n hour cooldown
-
-    let res: Result<Vec<darkredis::Value>, darkredis::Error> = redis
-        .run_commands(command)
-        .await
-        .map_err(|e| format!("communicating with redis: {:?}", e))?
-        .try_collect()
-        .await;
+    if let Some(rule) = broken_rule(chatid, &point, context).await? {
+        return telegram
+            .reply_and_close_keyboard(point.messageid, chat, rule)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending broken rule message: {}", e));
+    }
 
-    res.map_err(|e| format!("adding redis disaster data: {:?}", e))?;
+    //Starting a vote counts as giving a point, so votes can't be spammed either
+    if category.cooldown > 0 {
+        redis
+            .set_and_expire_seconds(
+                &cooldown_key,
+                b"",
+                category.cooldown.saturating_mul(3600).min(u32::MAX as u64) as u32,
+            )
+            .await
+            .map_err(|e| format!("setting point cooldown: {:?}", e))?;
+    }
 
-    //Send the update status message
-    let points: i64 = conn
-        .query_one(
-            include_sql!("disaster/getuserpoints.sql"),
-            params![chatid, receiverid],
-        )
-        .await
-        .map(|r| r.get(0))
-        .map_err(|e| format!("getting user points: {:?}", e))?;
+    //Only disaster points are put to a vote
+    if category.name == DEFAULT_CATEGORY {
+        let votes_needed: i64 = settings::get(chatid, "disastervotes", context)
+            .await?
+            .parse()
+            .unwrap_or(0);
+        if votes_needed > 0 {
+            return disastervote::start_vote(chat, point, votes_needed, telegram, context).await;
+        }
+    }
 
+    let points = give_point(chatid, &point, context).await?;
     telegram
         .reply_and_close_keyboard(
-            messageid,
-            chatid,
+            point.messageid,
+            chat,
             format!(
-                "{} now has {} disaster points.",
-                crate::util::get_user(chatid, receiverid, telegram, &context.config, &mut redis)
-                    .await,
-                points
+                "{} now has {} {} points.",
+                get_user(chatid, point.receiverid, telegram, context, &mut redis).await,
+                points,
+                category.name
             ),
         )
         .await
-        .map_err(|e| format!("sending disaster point count: {}", e))?;
+        .map_err(|e| format!("sending point count: {}", e))?;
 
     Ok(())
 }
 
-pub async fn show_points(
+//Checks the rules a chat has against members pumping up each other's points, returning why the
+//point can't be given if one of them is broken
+pub async fn broken_rule(
     chatid: i64,
+    point: &Point,
+    context: &Context,
+) -> Result<Option<String>, String> {
+    let setting = |name| async move {
+        settings::get(chatid, name, context)
+            .await
+            .map(|v| v.parse::<i64>().unwrap_or(0))
+    };
+    let min_messages = setting("pointminmessages").await?;
+    let receiver_cooldown = setting("pointreceivercooldown").await?;
+    let pair_limit = setting("pointpairlimit").await?;
+    let pair_days = setting("pointpairdays").await?;
+
+    let conn = context.db_pool.get().await.unwrap();
+    if min_messages > 0 {
+        let messages: i64 = conn
+            .query_one(
+                include_sql!("getusermessagecount.sql"),
+                params![chatid, point.giverid, None::<i64>],
+            )
+            .await
+            .map(|row| row.get(0))
+            .map_err(|e| format!("getting giver message count: {:?}", e))?;
+        if messages < min_messages {
+            return Ok(Some(format!(
+                "You need to have sent {} messages here before giving points, you have sent {}",
+                min_messages, messages
+            )));
+        }
+    }
+    if receiver_cooldown == 0 && pair_limit == 0 {
+        return Ok(None);
+    }
+
+    let now = Utc::now().timestamp();
+    let since = if pair_days > 0 {
+        now - pair_days * 24 * 3600
+    } else {
+        0
+    };
+    let row = conn
+        .query_one(
+            include_sql!("disaster/getpairlimits.sql"),
+            params![
+                chatid,
+                point.receiverid,
+                point.giverid,
+                point.category,
+                since
+            ],
+        )
+        .await
+        .map_err(|e| format!("getting point limits: {:?}", e))?;
+    let (last_received, pair_count): (Option<i64>, i64) = (row.get(0), row.get(1));
+
+    if let Some(last) = last_received {
+        let wait = last + receiver_cooldown * 3600 - now;
+        if receiver_cooldown > 0 && wait > 0 {
+            return Ok(Some(format!(
+                "They got a {} point recently, they can get another in {:0.1} hours",
+                point.category,
+                crate::util::seconds_to_hours(wait)
+            )));
+        }
+    }
+    if pair_limit > 0 && pair_count >= pair_limit {
+        let period = if pair_days > 0 {
+            format!("in {} days", pair_days)
+        } else {
+            "ever".to_string()
+        };
+        return Ok(Some(format!(
+            "You can only give the same member {} {} points {}",
+            pair_limit, point.category, period
+        )));
+    }
+    Ok(None)
+}
+
+//Gives a disaster point, as asked for by /disaster
+pub async fn add_disaster_point(
+    receiverid: i64,
+    giverid: i64,
+    chat: ChatTarget,
+    messageid: i64,
+    reason: Option<String>,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let category = categories::find(chat.chat_id, DEFAULT_CATEGORY, context)
+        .await?
+        .expect("disaster points always exist");
+    let point = Point {
+        giverid,
+        receiverid,
+        messageid,
+        reason,
+        category: category.name.clone(),
+        amount: 1,
+    };
+    add_point(point, &category, chat, telegram, context).await
+}
+
+//Usage: /give <category> <user> [<reason>]
+pub async fn give(
+    msg: &Message,
+    args: &[String],
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending give error message: {}", e))
+        };
+    }
+
+    if args.len() < 3 {
+        reply!(GIVE_USAGE.to_string());
+    }
+    let category = match categories::find(msg.chat.id, &args[1], context).await? {
+        Some(c) => c,
+        None => reply!(format!("There is no category named {}", args[1])),
+    };
+    let receiverid = match resolve_user(msg, &args[2], 2, telegram, context).await? {
+        Some(u) => u,
+        None => return Ok(()),
+    };
+    //Everything after the user is the reason
+    let reason = Some(args[3..].join(" ")).filter(|r| !r.is_empty());
+
+    let point = Point {
+        giverid: msg.from.id,
+        receiverid,
+        messageid: msg.id,
+        reason,
+        category: category.name.clone(),
+        amount: 1,
+    };
+    add_point(point, &category, msg.into(), telegram, context).await
+}
+
+//Gives or takes a point when someone replies "+1" or "-1" to a message, optionally followed by
+//the category. Without one, the chat has to have exactly one category with replies turned on.
+//Returns whether the reply was about a point.
+pub async fn handle_point_reply(
+    text: &str,
+    msg: &Message,
+    other_message: &Message,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<bool, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let (amount, name) = match words.as_slice() {
+        ["+1"] => (1, None),
+        ["-1"] => (-1, None),
+        ["+1", name] => (1, Some(name.to_lowercase())),
+        ["-1", name] => (-1, Some(name.to_lowercase())),
+        _ => return Ok(false),
+    };
+    if other_message.from.is_bot {
+        return Ok(false);
+    }
+
+    let mut candidates = categories::categories(msg.chat.id, context)
+        .await?
+        .into_iter()
+        .filter(|c| c.replies && name.as_ref().is_none_or(|n| *n == c.name));
+    let category = match (candidates.next(), candidates.next()) {
+        (Some(c), None) => c,
+        _ => return Ok(false),
+    };
+
+    let point = Point {
+        giverid: msg.from.id,
+        receiverid: other_message.from.id,
+        messageid: msg.id,
+        reason: None,
+        category: category.name.clone(),
+        amount,
+    };
+    add_point(point, &category, msg.into(), telegram, context).await?;
+    Ok(true)
+}
+
+//Stores a point, returning how many points the receiver has now
+pub async fn give_point(chatid: i64, point: &Point, context: &Context) -> Result<i64, String> {
     let conn = context.db_pool.get().await.unwrap();
-    let stmt = conn
-        .prepare_typed(include_sql!("disaster/getchatpoints.sql"), &[Type::INT8])
+    conn.execute(
+        include_sql!("disaster/addpoint.sql"),
+        params![
+            chatid,
+            point.receiverid,
+            point.giverid,
+            Utc::now().timestamp(),
+            point.reason,
+            point.messageid,
+            point.category,
+            point.amount
+        ],
+    )
+    .await
+    .map_err(|e| format!("adding a point: {:?}", e))?;
+
+    conn.query_one(
+        include_sql!("disaster/getuserpoints.sql"),
+        params![chatid, point.receiverid, point.category],
+    )
+    .await
+    .map(|r| r.get(0))
+    .map_err(|e| format!("getting user points: {:?}", e))
+}
+
+//Takes back the last point of a category the sender gave, if it was given recently enough
+pub async fn undo_point(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .reply_to(msg.id, msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending revoked point message: {}", e))
+        };
+    }
+
+    let category = args.get(1).map_or(DEFAULT_CATEGORY, |c| c.as_str());
+    let category = match categories::find(chatid, category, context).await? {
+        Some(c) => c,
+        None => reply!(format!("There is no category named {}", category)),
+    };
+    let grace = context.config.disaster.undo_grace as i64 * 60;
+    let conn = context.db_pool.get().await.unwrap();
+    //Points of an ended season are archived already, so they can't be taken back anymore
+    let season = current_season(chatid, &conn).await?;
+    let since = (msg.date - grace).max(season.started);
+    let revoked = conn
+        .query_opt(
+            include_sql!("disaster/revoke.sql"),
+            params![chatid, msg.from.id, since, category.name],
+        )
         .await
-        .unwrap();
+        .map_err(|e| format!("revoking point: {:?}", e))?;
+
+    let (receiverid, points): (i64, i64) = match revoked {
+        Some(row) => (row.get(0), row.get(1)),
+        None => reply!(format!(
+            "You haven't given a {} point this season in the last {} minutes",
+            category.name, context.config.disaster.undo_grace
+        )),
+    };
 
-    let points = conn
-        .query(&stmt, params![chatid])
+    //Giving a point again shouldn't have to wait for the cooldown of the one taken back
+    let mut redis = context.redis_pool.get().await;
+    redis
+        .del(cooldown_key(chatid, msg.from.id, &category.name))
         .await
-        .map_err(|e| format!("getting chat points: {:?}", e))?
-        .into_iter()
-        .map(|row| (row.get(0), row.get(1)))
-        .collect::<Vec<(i64, i64)>>();
+        .map_err(|e| format!("removing point cooldown: {:?}", e))?;
 
-    if points.is_empty() {
-        return telegram
-            .send_message_silent(chatid, "No points have been given yet".into())
+    reply!(format!(
+        "Took back the point, {} now has {} {} points.",
+        get_user(chatid, receiverid, telegram, context, &mut redis).await,
+        points,
+        category.name
+    ))
+}
+
+//Renders the point totals of a season and a page of the points given in it, returning the text
+//and the number of pages. None if no points have been given.
+async fn render_points(
+    chatid: i64,
+    category: &Category,
+    season: &Season,
+    page: i64,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<Option<(String, i64)>, String> {
+    let conn = context.db_pool.get().await.unwrap();
+    let points = match season.ended {
+        None => {
+            let stmt = conn
+                .prepare_typed(
+                    include_sql!("disaster/getchatpoints.sql"),
+                    &[Type::INT8, Type::TEXT],
+                )
+                .await
+                .unwrap();
+            conn.query(&stmt, params![chatid, category.name]).await
+        }
+        Some(_) => {
+            conn.query(
+                include_sql!("seasons/getstandings.sql"),
+                params![chatid, season.number, Some(&category.name)],
+            )
             .await
-            .map(|_| ())
-            .map_err(|e| format!("sending no points message: {}", e));
+        }
+    }
+    .map_err(|e| format!("getting chat points: {:?}", e))?
+    .into_iter()
+    .map(|row| (row.get(0), row.get(1)))
+    .collect::<Vec<(i64, i64)>>();
+
+    if points.is_empty() {
+        return Ok(None);
     }
 
+    let history = conn
+        .query(
+            include_sql!("disaster/gethistory.sql"),
+            params![
+                chatid,
+                HISTORY_PER_PAGE,
+                page * HISTORY_PER_PAGE,
+                season.started,
+                season.ended,
+                category.name
+            ],
+        )
+        .await
+        .map_err(|e| format!("getting point history: {:?}", e))?
+        .into_iter()
+        .map(|row| {
+            (
+                row.get(0),
+                row.get(1),
+                row.get(2),
+                row.get(3),
+                row.get(4),
+                row.get(5),
+            )
+        })
+        .collect::<Vec<(i64, i64, i64, Option<String>, i64, i64)>>();
+    let pages = match history.first() {
+        Some(h) => (h.5 + HISTORY_PER_PAGE - 1) / HISTORY_PER_PAGE,
+        None => 0,
+    };
+
     let mut redis = context.redis_pool.get().await;
-    let mut output = "```\n".to_string(); //Monospace the whole output
+    let ids: Vec<i64> = points
+        .iter()
+        .map(|p| p.1)
+        .chain(history.iter().flat_map(|h| [h.0, h.1]))
+        .collect();
+    let names = get_users(chatid, &ids, telegram, context, &mut redis).await;
+
+    //Monospace the whole output
+    let mut output = format!(
+        "<pre>{} {} points, season {}:\n",
+        escape_html(&category.emoji),
+        escape_html(&category.name),
+        season.number
+    );
     for (points, userid) in points {
-        //Add user points to output
-        let appendage = format!(
-            "{}: {}\n",
-            crate::util::get_user(chatid, userid, telegram, &context.config, &mut redis).await,
-            points
+        output += &escape_html(&format!("{}: {}\n", names[&userid], points));
+    }
+
+    if pages > 0 {
+        output += &format!("\nSent points, page {} of {}:\n", page + 1, pages);
+    }
+    for (giverid, receiverid, instant, reason, amount, _) in history {
+        let time_string = Local
+            .timestamp_opt(instant, 0)
+            .unwrap()
+            .format("%e %B %k:%M %:z");
+        let mut entry = format!(
+            "[{}] {} -> {}",
+            time_string, names[&giverid], names[&receiverid]
         );
+        if amount != 1 {
+            entry += &format!(" ({:+})", amount);
+        }
+        if let Some(r) = reason {
+            entry += &format!(": {}", r);
+        }
+        output += &escape_html(&entry);
+        output += "\n";
+    }
+    output += "</pre>";
 
-        output += &appendage;
+    Ok(Some((output, pages)))
+}
+
+//Finds the category and season asked for by [<category>] [--season <n>], defaulting to disaster
+//points of the current season. Returns None after telling the sender what's wrong.
+pub async fn points_scope(
+    msg: &Message,
+    args: &[String],
+    usage: &str,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<Option<(Category, Season)>, String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| None)
+                .map_err(|e| format!("sending points error message: {}", e))
+        };
     }
 
-    //Get a list of the last points that were given out
-    let last_disaster_key = format!("tg.lastdisasterpoints.{}", chatid).into_bytes();
-    let given_points = redis
-        .run_command(
-            Command::new("LRANGE")
-                .arg(&last_disaster_key)
-                .arg(b"0")
-                .arg(b"9"),
-        )
-        .await
-        .map_err(|e| format!("getting last disaster points given: {:?}", e))?
-        .unwrap_array();
-
-    output += "Last sent points:\n";
-
-    //Format these entries nicely
-    for value in given_points {
-        if let Value::String(v) = value {
-            let entry: LastDisaster = rmp_serde::from_slice(&v).unwrap();
-            let giver =
-                crate::util::get_user(chatid, entry.from, telegram, &context.config, &mut redis)
-                    .await;
-            let sender =
-                crate::util::get_user(chatid, entry.to, telegram, &context.config, &mut redis)
-                    .await;
-
-            let utc = DateTime::<Utc>::from_utc(
-                NaiveDateTime::from_timestamp_opt(entry.utc, 0).unwrap(),
-                Utc,
-            );
-            let time_string = utc.with_timezone(&Local).format("%e %B %k:%M %:z");
-
-            let appendage = format!("[{}] {} -> {}\n", time_string, giver, sender);
-            output += &appendage;
-        } else {
-            panic!("received invalid datatype from redis")
-        }
+    if let Some(flag) = arguments.unknown_flag(&["season"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, usage));
+    }
+    let category = match arguments.positional.as_slice() {
+        [] => DEFAULT_CATEGORY,
+        [category] => category.as_str(),
+        _ => reply!(usage.to_string()),
+    };
+    let category = match categories::find(chatid, category, context).await? {
+        Some(c) => c,
+        None => reply!(format!("There is no category named {}", category)),
+    };
+    let conn = context.db_pool.get().await.unwrap();
+    let season = match arguments.flag("season") {
+        Some([number]) => match number.parse::<i64>() {
+            Ok(n) => match get_season(chatid, n, &conn).await? {
+                Some(s) => s,
+                None => reply!(format!("There is no season {}", n)),
+            },
+            Err(_) => reply!(usage.to_string()),
+        },
+        Some(_) => reply!(usage.to_string()),
+        None => current_season(chatid, &conn).await?,
+    };
+
+    Ok(Some((category, season)))
+}
+
+//Usage: /points [<category>] [--season <n>]
+pub async fn show_points(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending points error message: {}", e))
+        };
     }
 
+    let (category, season) = match points_scope(msg, args, POINTS_USAGE, telegram, context).await? {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let (text, pages) =
+        match render_points(chatid, &category, &season, 0, telegram, context).await? {
+            Some(r) => r,
+            None => reply!(format!(
+                "No {} points have been given in season {}",
+                category.name, season.number
+            )),
+        };
+
+    let markup = if pages > 1 {
+        Some(page_buttons(
+            &format!("disasterpoints:{}:{}", category.name, season.number),
+            0,
+            pages,
+        ))
+    } else {
+        None
+    };
     telegram
-        .send_message_silently_with_markdown(chatid, format!("{}```", output))
+        .send_message_silently_with_html(msg, text, markup)
         .await
-        .map_err(|e| format!("sending disaster point count: {}", e))?;
+        .map(|_| ())
+        .map_err(|e| format!("sending point count: {}", e))
+}
 
-    Ok(())
+//Flips to another page of the points given when one of the page buttons is pressed. The
+//payload is category:season:page, or season:page for disaster points from before categories.
+pub async fn handle_callback(
+    callback: &CallbackQuery,
+    payload: &str,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let message = match callback.message {
+        Some(ref m) => m,
+        None => {
+            return telegram
+                .answer_callback_query(&callback.id, Some("These points are too old".into()))
+                .await
+        }
+    };
+    let parts: Vec<&str> = payload.split(':').collect();
+    let (category, season, page) = match parts.as_slice() {
+        [season, page] => (DEFAULT_CATEGORY, *season, *page),
+        [category, season, page] => (*category, *season, *page),
+        _ => return Err(format!("parsing points page '{}'", payload)),
+    };
+    let (season, page) = season
+        .parse::<i64>()
+        .ok()
+        .zip(page.parse::<i64>().ok())
+        .ok_or_else(|| format!("parsing points page '{}'", payload))?;
+
+    let chatid = message.chat.id;
+    let category = match categories::find(chatid, category, context).await? {
+        Some(c) => c,
+        None => {
+            return telegram
+                .answer_callback_query(&callback.id, Some("This category doesn't exist".into()))
+                .await
+        }
+    };
+    let conn = context.db_pool.get().await.unwrap();
+    let season = match get_season(chatid, season, &conn).await? {
+        Some(s) => s,
+        None => {
+            return telegram
+                .answer_callback_query(&callback.id, Some("This season doesn't exist".into()))
+                .await
+        }
+    };
+
+    if let Some((text, pages)) =
+        render_points(chatid, &category, &season, page, telegram, context).await?
+    {
+        telegram
+            .edit_message_with_html(
+                chatid,
+                message.id,
+                text,
+                Some(page_buttons(
+                    &format!("disasterpoints:{}:{}", category.name, season.number),
+                    page,
+                    pages,
+                )),
+            )
+            .await
+            .map_err(|e| format!("editing points: {}", e))?;
+    }
+
+    telegram.answer_callback_query(&callback.id, None).await
 }
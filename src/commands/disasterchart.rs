@@ -0,0 +1,158 @@
+use super::disaster::points_scope;
+use crate::{
+    include_sql, params, render,
+    telegram::{message::Message, Telegram},
+    util::get_users,
+    Context,
+};
+use chrono::prelude::*;
+use std::collections::HashMap;
+use tokio::task;
+
+const USAGE: &str = "Usage: /disasterchart [<category>] [--season <n>]";
+//More lines than colours in the palette can't be told apart
+const CHART_USERS: usize = 8;
+const MATRIX_USERS: usize = 12;
+
+//Usage: /disasterchart [<category>] [--season <n>]
+pub async fn disaster_chart(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let (category, season) = match points_scope(msg, args, USAGE, telegram, context).await? {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let events = conn
+        .query(
+            include_sql!("disaster/getevents.sql"),
+            params![chatid, category.name, season.started, season.ended],
+        )
+        .await
+        .map_err(|e| format!("getting point events: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+        .collect::<Vec<(i64, i64, i64, i64)>>();
+    if events.is_empty() {
+        return telegram
+            .send_message_silent(
+                msg,
+                format!(
+                    "No {} points have been given in season {}",
+                    category.name, season.number
+                ),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending no points message: {}", e));
+    }
+
+    //Points given before every point was logged only exist in the totals, so lines start there
+    let mut totals: HashMap<i64, i64> = conn
+        .query(
+            include_sql!("disaster/getunlogged.sql"),
+            params![
+                chatid,
+                category.name,
+                season.started,
+                season.ended,
+                season.number
+            ],
+        )
+        .await
+        .map_err(|e| format!("getting unlogged points: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    //Running total of every receiver, stepping at each point so it's clear when it was given
+    let start = events[0].2;
+    let end = season.ended.unwrap_or_else(|| Utc::now().timestamp());
+    let mut lines: HashMap<i64, Vec<(i64, f64)>> = totals
+        .iter()
+        .map(|(userid, points)| (*userid, vec![(start, *points as f64)]))
+        .collect();
+    for (_, receiverid, instant, amount) in &events {
+        let total = totals.entry(*receiverid).or_insert(0);
+        let line = lines
+            .entry(*receiverid)
+            .or_insert_with(|| vec![(start, 0.0)]);
+        line.push((*instant, *total as f64));
+        *total += amount;
+        line.push((*instant, *total as f64));
+    }
+    let mut ranked: Vec<(i64, i64)> = totals.into_iter().collect();
+    ranked.sort_by_key(|(userid, total)| (-total, *userid));
+
+    //The users giving and receiving the most points, each giver a row and receiver a column
+    let mut involvement: HashMap<i64, i64> = HashMap::new();
+    for (giverid, receiverid, _, _) in &events {
+        *involvement.entry(*giverid).or_insert(0) += 1;
+        *involvement.entry(*receiverid).or_insert(0) += 1;
+    }
+    let mut involved: Vec<(i64, i64)> = involvement.into_iter().collect();
+    involved.sort_by_key(|(userid, count)| (-count, *userid));
+    involved.truncate(MATRIX_USERS);
+    let index: HashMap<i64, usize> = involved
+        .iter()
+        .enumerate()
+        .map(|(i, (userid, _))| (*userid, i))
+        .collect();
+    let mut cells = vec![vec![0i64; involved.len()]; involved.len()];
+    for (giverid, receiverid, _, _) in &events {
+        if let (Some(row), Some(column)) = (index.get(giverid), index.get(receiverid)) {
+            cells[*row][*column] += 1;
+        }
+    }
+
+    let mut redis = context.redis_pool.get().await;
+    let ids: Vec<i64> = ranked
+        .iter()
+        .take(CHART_USERS)
+        .chain(involved.iter())
+        .map(|(userid, _)| *userid)
+        .collect();
+    let names = get_users(chatid, &ids, telegram, context, &mut redis).await;
+
+    let series: Vec<render::Series> = ranked
+        .iter()
+        .take(CHART_USERS)
+        .map(|(userid, total)| {
+            let mut points = lines.remove(userid).unwrap_or_default();
+            points.push((end, *total as f64));
+            render::Series {
+                label: format!("{} ({})", names[userid], total),
+                points,
+            }
+        })
+        .collect();
+    let labels: Vec<String> = involved
+        .iter()
+        .map(|(userid, _)| names[userid].to_string())
+        .collect();
+
+    let title = format!("{} points in season {}", category.name, season.number);
+    let chart = task::block_in_place(|| render::line_chart(&title, &series))?;
+    let matrix_title = format!("Who gives {} points to whom", category.name);
+    let matrix = task::block_in_place(|| render::heatmap(&matrix_title, &labels, &cells))?;
+
+    telegram
+        .send_png_lossless(msg, chart, None, true)
+        .await
+        .map_err(|e| format!("sending points chart: {}", e))?;
+    telegram
+        .send_png_lossless(
+            msg,
+            matrix,
+            Some("Rows are the givers and columns the receivers".into()),
+            true,
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending points matrix: {}", e))
+}
@@ -0,0 +1,325 @@
+use super::{
+    disaster::{broken_rule, give_point, Point},
+    settings,
+};
+use crate::{
+    telegram::{callbackquery::CallbackQuery, ChatTarget, Telegram},
+    util::{escape_html, get_users},
+    Context,
+};
+use chrono::prelude::*;
+use darkredis::Value;
+use serde::{Deserialize, Serialize};
+
+//Votes which haven't ended yet, as chatid.messageid of their vote message
+const OPEN_VOTES_KEY: &str = "tg.disastervote.open";
+//How often votes are checked for having run out of time, in seconds
+const TICK_INTERVAL: u64 = 60;
+//Votes are kept around for a while after their deadline so they can still be closed
+const VOTE_EXPIRY_MARGIN: u32 = 3600;
+
+//A point waiting for members to confirm it
+#[derive(Serialize, Deserialize)]
+struct PointVote {
+    point: Point,
+    needed: i64,
+    deadline: i64,
+}
+
+//Seconds a vote with this many seconds left is kept around for
+fn vote_expiry(seconds_left: i64) -> u32 {
+    (seconds_left.clamp(0, u32::MAX as i64) as u32).saturating_add(VOTE_EXPIRY_MARGIN)
+}
+
+fn vote_key(chatid: i64, messageid: i64) -> String {
+    format!("tg.disastervote.{}.{}", chatid, messageid)
+}
+
+//Hash of voter id to up or down
+fn voters_key(chatid: i64, messageid: i64) -> String {
+    format!("tg.disastervoters.{}.{}", chatid, messageid)
+}
+
+fn vote_buttons(up: i64, down: i64) -> serde_json::Value {
+    serde_json::json!({ "inline_keyboard": [[
+        { "text": format!("👍 {}", up), "callback_data": "disastervote:up" },
+        { "text": format!("👎 {}", down), "callback_data": "disastervote:down" },
+    ]] })
+}
+
+async fn vote_text(
+    chatid: i64,
+    vote: &PointVote,
+    status: &str,
+    telegram: &Telegram,
+    context: &Context,
+) -> String {
+    let mut redis = context.redis_pool.get().await;
+    let point = &vote.point;
+    let names = get_users(
+        chatid,
+        &[point.giverid, point.receiverid],
+        telegram,
+        context,
+        &mut redis,
+    )
+    .await;
+    let giver = escape_html(&names[&point.giverid].to_string());
+    let receiver = escape_html(&names[&point.receiverid].to_string());
+    let mut text = if point.amount < 0 {
+        format!(
+            "{} wants to take a {} point from {}",
+            giver, point.category, receiver
+        )
+    } else {
+        format!(
+            "{} wants to give {} a {} point",
+            giver, receiver, point.category
+        )
+    };
+    if let Some(ref reason) = point.reason {
+        text += &format!(": <i>{}</i>", escape_html(reason));
+    }
+    format!("{}\n{}", text, status)
+}
+
+pub async fn start_vote(
+    chat: ChatTarget,
+    point: Point,
+    needed: i64,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = chat.chat_id;
+    let minutes: i64 = settings::get(chatid, "disastervotetime", context)
+        .await?
+        .parse()
+        .unwrap_or(60);
+    let vote = PointVote {
+        point,
+        needed,
+        deadline: Utc::now().timestamp() + minutes * 60,
+    };
+
+    let status = format!("{} confirmations needed within {} minutes", needed, minutes);
+    let text = vote_text(chatid, &vote, &status, telegram, context).await;
+    let sent = telegram
+        .send_message_silently_with_html(chat, text, Some(vote_buttons(0, 0)))
+        .await
+        .map_err(|e| format!("sending disaster point vote: {}", e))?;
+
+    let mut redis = context.redis_pool.get().await;
+    redis
+        .set_and_expire_seconds(
+            vote_key(chatid, sent.id),
+            rmp_serde::to_vec(&vote).unwrap(),
+            vote_expiry(minutes * 60),
+        )
+        .await
+        .map_err(|e| format!("storing disaster point vote: {:?}", e))?;
+    redis
+        .sadd(OPEN_VOTES_KEY, format!("{}.{}", chatid, sent.id))
+        .await
+        .map_err(|e| format!("adding open disaster point vote: {:?}", e))?;
+    Ok(())
+}
+
+//Takes the vote off the open votes, returning whether it was still open. Makes sure only one
+//voter or the ticker gets to end it.
+async fn claim_vote(
+    chatid: i64,
+    messageid: i64,
+    redis: &mut darkredis::Connection,
+) -> Result<bool, String> {
+    let claimed = redis
+        .srem(OPEN_VOTES_KEY, format!("{}.{}", chatid, messageid))
+        .await
+        .map_err(|e| format!("removing open disaster point vote: {:?}", e))?;
+    if claimed {
+        redis
+            .del_slice(&[vote_key(chatid, messageid), voters_key(chatid, messageid)])
+            .await
+            .map_err(|e| format!("removing disaster point vote: {:?}", e))?;
+    }
+    Ok(claimed)
+}
+
+//Handles the 👍 and 👎 buttons of a vote
+pub async fn handle_callback(
+    callback: &CallbackQuery,
+    choice: &str,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    macro_rules! answer {
+        ($text:expr) => {
+            return telegram
+                .answer_callback_query(&callback.id, Some($text.into()))
+                .await
+        };
+    }
+
+    let message = match callback.message {
+        Some(ref m) => m,
+        None => answer!("This vote is over"),
+    };
+    let chatid = message.chat.id;
+    let mut redis = context.redis_pool.get().await;
+    let vote: PointVote = match redis
+        .get(vote_key(chatid, message.id))
+        .await
+        .map_err(|e| format!("getting disaster point vote: {:?}", e))?
+    {
+        Some(v) => rmp_serde::from_slice(&v).unwrap(),
+        None => answer!("This vote is over"),
+    };
+    if Utc::now().timestamp() > vote.deadline {
+        answer!("This vote is over");
+    }
+    let voterid = callback.from.id;
+    if voterid == vote.point.giverid || voterid == vote.point.receiverid {
+        answer!("You can't vote on this point");
+    }
+
+    if choice != "up" && choice != "down" {
+        return Err(format!("unknown disaster point vote '{}'", choice));
+    }
+    let voters = voters_key(chatid, message.id);
+    let previous = redis
+        .hget(&voters, voterid.to_string())
+        .await
+        .map_err(|e| format!("getting earlier disaster point vote: {:?}", e))?;
+    if previous.as_deref() == Some(choice.as_bytes()) {
+        answer!("You already voted that");
+    }
+    redis
+        .hset(&voters, voterid.to_string(), choice)
+        .await
+        .map_err(|e| format!("storing disaster point vote: {:?}", e))?;
+    redis
+        .expire_seconds(&voters, vote_expiry(vote.deadline - Utc::now().timestamp()))
+        .await
+        .map_err(|e| format!("setting disaster point voters expiry: {:?}", e))?;
+    let votes = redis
+        .hvals(&voters)
+        .await
+        .map_err(|e| format!("getting disaster point votes: {:?}", e))?;
+    let count = |wanted: &str| {
+        votes
+            .iter()
+            .filter(|v| matches!(v, Value::String(s) if s == wanted.as_bytes()))
+            .count() as i64
+    };
+    let (up, down) = (count("up"), count("down"));
+
+    let (status, buttons) = if up >= vote.needed {
+        if !claim_vote(chatid, message.id, &mut redis).await? {
+            answer!("This vote is over");
+        }
+        //Points given while the vote was open count towards the rules too
+        match broken_rule(chatid, &vote.point, context).await? {
+            Some(rule) => (
+                format!(
+                    "Confirmed by {} members, but it can't be given: {}",
+                    up,
+                    escape_html(&rule)
+                ),
+                None,
+            ),
+            None => {
+                let points = give_point(chatid, &vote.point, context).await?;
+                (
+                    format!(
+                        "Confirmed by {} members, they now have {} points.",
+                        up, points
+                    ),
+                    None,
+                )
+            }
+        }
+    } else if down >= vote.needed {
+        if !claim_vote(chatid, message.id, &mut redis).await? {
+            answer!("This vote is over");
+        }
+        (format!("Rejected by {} members.", down), None)
+    } else {
+        (
+            format!("{} of {} confirmations so far", up, vote.needed),
+            Some(vote_buttons(up, down)),
+        )
+    };
+
+    let text = vote_text(chatid, &vote, &status, telegram, context).await;
+    telegram
+        .edit_message_with_html(chatid, message.id, text, buttons)
+        .await
+        .map_err(|e| format!("editing disaster point vote: {}", e))?;
+    telegram.answer_callback_query(&callback.id, None).await
+}
+
+//Closes the votes whose time has run out
+async fn close_expired_votes(telegram: &Telegram, context: &Context) -> Result<(), String> {
+    let mut redis = context.redis_pool.get().await;
+    let open = redis
+        .smembers(OPEN_VOTES_KEY)
+        .await
+        .map_err(|e| format!("getting open disaster point votes: {:?}", e))?;
+
+    let now = Utc::now().timestamp();
+    for member in open {
+        let member = String::from_utf8_lossy(&member).into_owned();
+        let (chatid, messageid) = match member
+            .split_once('.')
+            .and_then(|(c, m)| Some((c.parse::<i64>().ok()?, m.parse::<i64>().ok()?)))
+        {
+            Some(ids) => ids,
+            None => {
+                warn!("Invalid open disaster point vote {}", member);
+                continue;
+            }
+        };
+
+        let vote: Option<PointVote> = redis
+            .get(vote_key(chatid, messageid))
+            .await
+            .map_err(|e| format!("getting disaster point vote: {:?}", e))?
+            .map(|v| rmp_serde::from_slice(&v).unwrap());
+        match vote {
+            //Deleted along with the rest of the chat's data, so there's nothing to close
+            None => {
+                claim_vote(chatid, messageid, &mut redis).await?;
+            }
+            Some(vote) if vote.deadline < now => {
+                if !claim_vote(chatid, messageid, &mut redis).await? {
+                    continue;
+                }
+                let text = vote_text(
+                    chatid,
+                    &vote,
+                    "Not enough members confirmed in time.",
+                    telegram,
+                    context,
+                )
+                .await;
+                if let Err(e) = telegram
+                    .edit_message_with_html(chatid, messageid, text, None)
+                    .await
+                {
+                    warn!("Couldn't close disaster point vote {}: {}", member, e);
+                }
+            }
+            Some(_) => (),
+        }
+    }
+    Ok(())
+}
+
+pub async fn ticker(telegram: &Telegram, context: &Context) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(TICK_INTERVAL));
+    loop {
+        interval.tick().await;
+        if let Err(e) = close_expired_votes(telegram, context).await {
+            error!("Closing expired disaster point votes failed at '{}'", e);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use super::pickuser::resolve_user;
+use crate::{
+    include_sql, params, render,
+    telegram::{message::Message, Telegram},
+    tokenizer::{is_emoji, normalize_emoji, tokenize},
+    util::{get_user, topic_scope, Arguments},
+    Context,
+};
+use std::collections::HashMap;
+use tokio::task;
+
+const EMOJI_CHARTED: usize = 30;
+//Captions are limited to 1024 characters, so only the top of the list fits
+const EMOJI_LISTED: usize = 15;
+
+//How many times an emoji was used in each way
+#[derive(Default)]
+struct EmojiUses {
+    text: i64,
+    custom: i64,
+    sticker: i64,
+}
+
+impl EmojiUses {
+    fn total(&self) -> i64 {
+        self.text + self.custom + self.sticker
+    }
+}
+
+const USAGE: &str = "Usage: /emojistats [<user>] [--topic [<name>]]";
+
+pub async fn emojistats(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending emojistats error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, USAGE));
+    }
+    let userid = match arguments.positional.as_slice() {
+        [name] => match resolve_user(msg, name, 1, telegram, context).await? {
+            Some(u) => Some(u),
+            None => return Ok(()),
+        },
+        [] => None,
+        _ => reply!(USAGE.to_string()),
+    };
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let mut uses: HashMap<String, EmojiUses> = HashMap::new();
+
+    let messages = conn
+        .query(
+            include_sql!("emoji/getmessages.sql"),
+            params![chatid, userid, topic],
+        )
+        .await
+        .map_err(|e| format!("getting messages: {:?}", e))?;
+    for row in messages {
+        for token in tokenize(row.get(0)) {
+            if is_emoji(&token) {
+                uses.entry(token).or_default().text += 1;
+            }
+        }
+    }
+
+    //The fallback of a custom emoji is part of the message text, so move those uses over
+    let custom = conn
+        .query(
+            include_sql!("emoji/getcustomemoji.sql"),
+            params![chatid, userid, topic],
+        )
+        .await
+        .map_err(|e| format!("getting custom emoji: {:?}", e))?;
+    for row in custom {
+        let count: i64 = row.get(1);
+        let entry = uses.entry(normalize_emoji(row.get(0))).or_default();
+        entry.text = (entry.text - count).max(0);
+        entry.custom += count;
+    }
+
+    let stickers = conn
+        .query(
+            include_sql!("emoji/getstickeremoji.sql"),
+            params![chatid, userid, topic],
+        )
+        .await
+        .map_err(|e| format!("getting sticker emoji: {:?}", e))?;
+    for row in stickers {
+        uses.entry(normalize_emoji(row.get(0))).or_default().sticker += row.get::<_, i64>(1);
+    }
+
+    let mut ranked: Vec<(String, EmojiUses)> =
+        uses.into_iter().filter(|(_, u)| u.total() > 0).collect();
+    if ranked.is_empty() {
+        reply!("I haven't seen any emoji yet".into());
+    }
+    ranked.sort_unstable_by(|a, b| b.1.total().cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0)));
+
+    let mut caption = match userid {
+        Some(u) => {
+            let mut redis = context.redis_pool.get().await;
+            format!(
+                "Emoji used by {}:\n",
+                get_user(chatid, u, telegram, context, &mut redis).await
+            )
+        }
+        None => "Emoji used in this chat:\n".to_string(),
+    };
+    for (emoji, u) in ranked.iter().take(EMOJI_LISTED) {
+        caption += &format!(
+            "{} {} (text {}, custom {}, stickers {})\n",
+            emoji,
+            u.total(),
+            u.text,
+            u.custom,
+            u.sticker
+        );
+    }
+
+    let bars: Vec<(String, i64)> = ranked
+        .into_iter()
+        .take(EMOJI_CHARTED)
+        .map(|(emoji, u)| (emoji, u.total()))
+        .collect();
+    let image = task::block_in_place(|| render::bar_chart(&bars, "Noto Color Emoji"))?;
+
+    telegram
+        .send_png_lossless(msg, image, Some(caption), true)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending emoji stats: {}", e))
+}
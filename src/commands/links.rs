@@ -0,0 +1,173 @@
+use super::pickuser::resolve_user;
+use crate::{
+    include_sql,
+    indexer::IndexedMessage,
+    links, params,
+    telegram::{message::Message, Telegram},
+    util::{align_text_after, get_user, topic_scope, Arguments},
+    Context,
+};
+use chrono::prelude::*;
+
+const DOMAINS_SHOWN: i64 = 10;
+const SHARERS_SHOWN: i64 = 10;
+const RESHARED_SHOWN: i64 = 5;
+
+const USAGE: &str = "Usage: /links [<user>] [--topic [<name>]]";
+
+//Points out when a link in a freshly logged message was already posted within the chat's window
+pub async fn check_repost(
+    msg: &Message,
+    indexed: &IndexedMessage<'_>,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let days: i64 = super::settings::get(msg.chat.id, "repostwindow", context)
+        .await?
+        .parse()
+        .unwrap_or(0);
+    if days == 0 {
+        return Ok(());
+    }
+
+    let found = links::extract(indexed.text, indexed.entity_links.iter().copied());
+    if found.is_empty() {
+        return Ok(());
+    }
+
+    let since = msg.date - days * 24 * 3600;
+    let conn = context.db_pool.get().await.unwrap();
+    for link in found {
+        let earlier = conn
+            .query_opt(
+                include_sql!("links/getearlierpost.sql"),
+                params![msg.chat.id, link.url, msg.id, since],
+            )
+            .await
+            .map_err(|e| format!("getting earlier post of link: {:?}", e))?;
+
+        if let Some(row) = earlier {
+            let mut redis = context.redis_pool.get().await;
+            let poster = get_user(msg.chat.id, row.get(0), telegram, context, &mut redis).await;
+            let date = Local
+                .timestamp_opt(row.get(1), 0)
+                .unwrap()
+                .format(&context.config.general.time_format);
+            return telegram
+                .reply_to(
+                    msg.id,
+                    msg,
+                    format!("Already posted by {} on {}", poster, date),
+                )
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending repost message: {}", e));
+        }
+    }
+    Ok(())
+}
+
+pub async fn links(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending links error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, USAGE));
+    }
+    let userid = match arguments.positional.as_slice() {
+        [name] => match resolve_user(msg, name, 1, telegram, context).await? {
+            Some(u) => Some(u),
+            None => return Ok(()),
+        },
+        [] => None,
+        _ => reply!(USAGE.to_string()),
+    };
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let domains = conn
+        .query(
+            include_sql!("links/gettopdomains.sql"),
+            params![chatid, userid, DOMAINS_SHOWN, topic],
+        )
+        .await
+        .map_err(|e| format!("getting top domains: {:?}", e))?;
+    if domains.is_empty() {
+        return telegram
+            .send_message_silent(msg, "I haven't seen any links yet".into())
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending no links message: {}", e));
+    }
+
+    let mut redis = context.redis_pool.get().await;
+    let mut output = match userid {
+        Some(u) => format!(
+            "```\nLinks shared by {}\n",
+            get_user(chatid, u, telegram, context, &mut redis).await
+        ),
+        None => "```\nLinks shared in this chat\n".to_string(),
+    };
+
+    let mut table = String::new();
+    for row in domains {
+        table += &format!("{}: {}\n", row.get::<_, String>(0), row.get::<_, i64>(1));
+    }
+    output += &format!("\nTop domains:\n{}", align_text_after(':', table));
+
+    if userid.is_none() {
+        let sharers = conn
+            .query(
+                include_sql!("links/gettopsharers.sql"),
+                params![chatid, SHARERS_SHOWN, topic],
+            )
+            .await
+            .map_err(|e| format!("getting top link sharers: {:?}", e))?;
+        let mut table = String::new();
+        for row in sharers {
+            table += &format!(
+                "{}: {}\n",
+                get_user(chatid, row.get(0), telegram, context, &mut redis).await,
+                row.get::<_, i64>(1)
+            );
+        }
+        output += &format!("\nTop sharers:\n{}", align_text_after(':', table));
+    }
+
+    let reshared = conn
+        .query(
+            include_sql!("links/gettopreshared.sql"),
+            params![chatid, userid, RESHARED_SHOWN, topic],
+        )
+        .await
+        .map_err(|e| format!("getting most reshared links: {:?}", e))?;
+    if !reshared.is_empty() {
+        output += "\nMost reshared links:\n";
+        for row in reshared {
+            output += &format!("{}x {}\n", row.get::<_, i64>(1), row.get::<_, String>(0));
+        }
+    }
+
+    telegram
+        .send_message_silently_with_markdown(msg, format!("{}```", output))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending link statistics: {}", e))
+}
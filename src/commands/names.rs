@@ -0,0 +1,88 @@
+use super::pickuser::resolve_user;
+use crate::{
+    include_sql, params,
+    telegram::{message::Message, Telegram},
+    util::get_user,
+    Context,
+};
+use chrono::prelude::*;
+
+const USAGE: &str = "Usage: /names [<user>]";
+
+//Shows every name a user has gone by in the chat, oldest first
+pub async fn names(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending names error message: {}", e))
+        };
+    }
+
+    let userid = match &args[1..] {
+        [] => msg.from.id,
+        [name] => match resolve_user(msg, name, 1, telegram, context).await? {
+            Some(u) => u,
+            None => return Ok(()),
+        },
+        _ => reply!(USAGE.to_string()),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let names = conn
+        .query(include_sql!("names/get.sql"), params![chatid, userid])
+        .await
+        .map_err(|e| format!("getting name history: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+        .collect::<Vec<(String, Option<String>, Option<String>, i64)>>();
+
+    if names.is_empty() {
+        reply!("I don't know of any names they have had".into());
+    }
+
+    let mut table = String::new();
+    for (first_name, last_name, username, instant) in names {
+        //Names from before the history was kept have no known time
+        let date = if instant == 0 {
+            "Before tracking".to_string()
+        } else {
+            Local
+                .timestamp_opt(instant, 0)
+                .unwrap()
+                .format(&context.config.general.time_format)
+                .to_string()
+        };
+        let mut name = first_name;
+        if let Some(last) = last_name {
+            name += &format!(" {}", last);
+        }
+        if let Some(u) = username {
+            name += &format!(" (@{})", u);
+        }
+        //Not aligned, as the time format may contain colons
+        table += &format!("{} - {}\n", date, name);
+    }
+
+    let mut redis = context.redis_pool.get().await;
+    telegram
+        .send_message_silently_with_markdown(
+            msg,
+            format!(
+                "```\nNames of {}:\n{}```",
+                get_user(chatid, userid, telegram, context, &mut redis).await,
+                table
+            ),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending name history: {}", e))
+}
@@ -0,0 +1,284 @@
+use super::pickuser::resolve_user;
+use crate::{
+    image::decode,
+    include_sql, params,
+    render::{background, surface_to_png},
+    telegram::{message::Message, Telegram},
+    util::{get_user, rgba_to_cairo, topic_scope, Arguments},
+    Context,
+};
+use cairo::{Format, ImageSurface};
+use chrono::prelude::*;
+use tokio::task;
+
+const PACKS_SHOWN: i64 = 10;
+const FAVOURITES_SHOWN: usize = 10;
+//How long pack metadata is trusted before asking Telegram again, in seconds
+const PACK_REFRESH: i64 = 7 * 24 * 3600;
+
+const USAGE: &str = "Usage: /packs [<user>] [--topic [<name>]]";
+
+struct PackInfo {
+    title: String,
+    sticker_count: i32,
+    thumbnail_id: Option<String>,
+}
+
+//A row in the rendered leaderboard
+struct PackRow {
+    title: String,
+    uses: i64,
+    //Distinct stickers from the pack which have been used, out of how many it has
+    used: i64,
+    sticker_count: Option<i32>,
+    thumbnail: Option<Vec<u8>>,
+}
+
+//Gets pack metadata from the cache, asking Telegram when it's missing or old.
+//Packs can be deleted, so stale data is used when Telegram doesn't know the pack anymore.
+async fn pack_info(name: &str, telegram: &Telegram, context: &Context) -> Option<PackInfo> {
+    let conn = context.db_pool.get().await.unwrap();
+    let cached = conn
+        .query_opt(include_sql!("packs/get.sql"), params![name])
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to get cached sticker set {}: {:?}", name, e);
+            None
+        })
+        .map(|row| {
+            let info = PackInfo {
+                title: row.get(0),
+                sticker_count: row.get(1),
+                thumbnail_id: row.get(2),
+            };
+            (info, row.get::<_, i64>(3))
+        });
+
+    let now = Utc::now().timestamp();
+    if matches!(cached, Some((_, fetched)) if now - fetched < PACK_REFRESH) {
+        return cached.map(|(info, _)| info);
+    }
+
+    let set = match telegram.get_sticker_set(name).await {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Couldn't get sticker set {}: {}", name, e);
+            return cached.map(|(info, _)| info);
+        }
+    };
+    let thumbnail_id = set.image_file_id().map(|id| id.to_string());
+    let info = PackInfo {
+        title: set.title,
+        sticker_count: set.stickers.len() as i32,
+        thumbnail_id,
+    };
+    if let Err(e) = conn
+        .execute(
+            include_sql!("packs/set.sql"),
+            params![name, info.title, info.sticker_count, info.thumbnail_id, now],
+        )
+        .await
+    {
+        error!("Failed to cache sticker set {}: {:?}", name, e);
+    }
+    Some(info)
+}
+
+fn render_packs_to_surface(rows: Vec<PackRow>) -> Result<ImageSurface, cairo::Error> {
+    let width = 1200.0;
+    let row_height = 140.0;
+    let padding = 20.0;
+    let thumbnail_size = row_height - padding;
+    let text_x = padding * 2.0 + thumbnail_size;
+    let bar_width = width - text_x - padding;
+    let height = row_height * rows.len() as f64 + padding;
+    let max_uses = rows.iter().map(|r| r.uses).max().unwrap_or(1) as f64;
+
+    let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32)?;
+    let cairo = cairo::Context::new(&surface)?;
+    background(&cairo, width, height)?;
+    cairo.select_font_face("Hack", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+
+    for (index, row) in rows.into_iter().enumerate() {
+        let y = padding + index as f64 * row_height;
+
+        let image = row.thumbnail.and_then(|f| match decode(&f) {
+            Ok(i) => Some(i),
+            Err(e) => {
+                info!(
+                    "Couldn't decode pack thumbnail, drawing a placeholder: {}",
+                    e
+                );
+                None
+            }
+        });
+        match image {
+            Some(mut image) => {
+                unsafe { rgba_to_cairo(image.data.as_mut_ptr(), image.data.len()) };
+                let format = Format::ARgb32;
+                let stride = format.stride_for_width(image.width as u32)?;
+                let thumbnail = ImageSurface::create_for_data(
+                    image.data,
+                    format,
+                    image.width,
+                    image.height,
+                    stride,
+                )?;
+                let scale = (thumbnail_size / f64::from(image.width))
+                    .min(thumbnail_size / f64::from(image.height));
+                cairo.save()?;
+                cairo.translate(padding, y);
+                cairo.scale(scale, scale);
+                cairo.set_source_surface(&thumbnail, 0.0, 0.0)?;
+                cairo.paint()?;
+                cairo.restore()?;
+                thumbnail.finish();
+            }
+            None => {
+                cairo.rectangle(padding, y, thumbnail_size, thumbnail_size);
+                cairo.set_source_rgb(0.3, 0.3, 0.3);
+                cairo.fill()?;
+            }
+        }
+
+        cairo.set_source_rgb(1.0, 1.0, 1.0);
+        cairo.set_font_size(30.0);
+        cairo.move_to(text_x, y + 35.0);
+        cairo.show_text(&row.title)?;
+
+        let coverage = match row.sticker_count {
+            Some(count) => format!("{} uses, {} of {} stickers used", row.uses, row.used, count),
+            None => format!("{} uses, {} stickers used", row.uses, row.used),
+        };
+        cairo.set_font_size(20.0);
+        cairo.move_to(text_x, y + 70.0);
+        cairo.show_text(&coverage)?;
+
+        cairo.set_source_rgb(0.5, 0.5, 1.0);
+        cairo.rectangle(
+            text_x,
+            y + 85.0,
+            bar_width * row.uses as f64 / max_uses,
+            25.0,
+        );
+        cairo.fill()?;
+    }
+    Ok(surface)
+}
+
+fn render_packs(rows: Vec<PackRow>) -> Result<Vec<u8>, String> {
+    let surface = render_packs_to_surface(rows).map_err(|e| format!("Cairo error: {:?}", e))?;
+    surface_to_png(&surface)
+}
+
+pub async fn packs(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending packs error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, USAGE));
+    }
+    let userid = match arguments.positional.as_slice() {
+        [name] => match resolve_user(msg, name, 1, telegram, context).await? {
+            Some(u) => Some(u),
+            None => return Ok(()),
+        },
+        [] => None,
+        _ => reply!(USAGE.to_string()),
+    };
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let top = conn
+        .query(
+            include_sql!("packs/gettop.sql"),
+            params![chatid, userid, PACKS_SHOWN, topic],
+        )
+        .await
+        .map_err(|e| format!("getting top packs: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect::<Vec<(String, i64, i64)>>();
+    if top.is_empty() {
+        return telegram
+            .send_message_silent(msg, "I haven't seen any sticker packs yet".into())
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending no packs message: {}", e));
+    }
+
+    let mut redis = context.redis_pool.get().await;
+    let mut rows = Vec::with_capacity(top.len());
+    for (name, uses, used) in top {
+        let info = pack_info(&name, telegram, context).await;
+        let thumbnail = match info.as_ref().and_then(|i| i.thumbnail_id.as_ref()) {
+            Some(id) => telegram.download_file(&mut redis, id).await.ok(),
+            None => None,
+        };
+        rows.push(PackRow {
+            title: info.as_ref().map_or(name, |i| i.title.clone()),
+            uses,
+            used,
+            sticker_count: info.map(|i| i.sticker_count),
+            thumbnail,
+        });
+    }
+
+    let caption = match userid {
+        Some(u) => format!(
+            "Favourite sticker packs of {}",
+            get_user(chatid, u, telegram, context, &mut redis).await
+        ),
+        None => {
+            let mut favourites = conn
+                .query(
+                    include_sql!("packs/getfavourites.sql"),
+                    params![chatid, topic],
+                )
+                .await
+                .map_err(|e| format!("getting favourite packs: {:?}", e))?
+                .into_iter()
+                .map(|row| (row.get(0), row.get(1), row.get(2)))
+                .collect::<Vec<(i64, String, i64)>>();
+            favourites.sort_by_key(|(_, _, uses)| -uses);
+
+            let mut caption = "Most used sticker packs in this chat\n\nFavourites:".to_string();
+            for (userid, name, uses) in favourites.into_iter().take(FAVOURITES_SHOWN) {
+                let title = pack_info(&name, telegram, context)
+                    .await
+                    .map_or(name, |i| i.title);
+                caption += &format!(
+                    "\n{}: {} ({})",
+                    get_user(chatid, userid, telegram, context, &mut redis).await,
+                    title,
+                    uses
+                );
+            }
+            caption
+        }
+    };
+
+    let image = task::block_in_place(|| render_packs(rows))?;
+    telegram
+        .send_png_lossless(msg, image, Some(caption), true)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending pack leaderboard: {}", e))
+}
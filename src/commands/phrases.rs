@@ -0,0 +1,206 @@
+use super::pickuser::resolve_user;
+use super::settings;
+use crate::{
+    include_sql,
+    indexer::MAX_NGRAM,
+    params,
+    telegram::{message::Message, Telegram},
+    tokenizer::tokenize,
+    util::{align_text_after, get_user, topic_scope, Arguments},
+    Context,
+};
+use std::collections::HashMap;
+
+const PHRASES_SHOWN: usize = 10;
+//Phrases made up of only stop words are thrown away, so fetch more than what is shown
+const PHRASES_FETCHED: i64 = 200;
+const PHRASE_USERS_SHOWN: usize = 10;
+
+const PHRASES_USAGE: &str = "Usage: /phrases [<user>] [--topic [<name>]]";
+const PHRASE_USAGE: &str = "Usage: /phrase \"some words\" [--topic [<name>]]";
+
+pub async fn phrases(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending phrases error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, PHRASES_USAGE));
+    }
+    let userid = match arguments.positional.as_slice() {
+        [name] => match resolve_user(msg, name, 1, telegram, context).await? {
+            Some(u) => Some(u),
+            None => return Ok(()),
+        },
+        [] => None,
+        _ => reply!(PHRASES_USAGE.to_string()),
+    };
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let stop_words = settings::stop_words(chatid, context).await?;
+    let conn = context.db_pool.get().await.unwrap();
+    let mut output = match userid {
+        Some(u) => {
+            let mut redis = context.redis_pool.get().await;
+            format!(
+                "```\nFavourite phrases of {}:\n",
+                get_user(chatid, u, telegram, context, &mut redis).await
+            )
+        }
+        None => "```\nMost used phrases in this chat:\n".to_string(),
+    };
+
+    let mut found_any = false;
+    for n in 2..=MAX_NGRAM {
+        let ranked: Vec<(String, i64)> = conn
+            .query(
+                include_sql!("phrases/gettop.sql"),
+                params![chatid, n as i16, userid, PHRASES_FETCHED, topic],
+            )
+            .await
+            .map_err(|e| format!("getting top {}-grams: {:?}", n, e))?
+            .into_iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        let top = ranked
+            .into_iter()
+            .filter(|(ngram, _)| !ngram.split(' ').all(|w| stop_words.contains(w)))
+            .take(PHRASES_SHOWN)
+            .collect::<Vec<(String, i64)>>();
+
+        if top.is_empty() {
+            continue;
+        }
+        found_any = true;
+
+        let mut table = String::new();
+        for (ngram, uses) in top {
+            table += &format!("{}: {}\n", ngram, uses);
+        }
+        output += &format!("\n{} words:\n{}", n, align_text_after(':', table));
+    }
+
+    if !found_any {
+        reply!("I haven't seen any phrases yet".into());
+    }
+
+    telegram
+        .send_message_silently_with_markdown(msg, format!("{}```", output))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending phrases: {}", e))
+}
+
+//Counts how many times each user has said an exact phrase
+pub async fn phrase(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending phrase error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, PHRASE_USAGE));
+    }
+    let input = arguments.positional.join(" ");
+    let tokens = tokenize(input.trim_matches(&['"', '“', '”'][..]));
+    if tokens.is_empty() {
+        reply!(PHRASE_USAGE.to_string());
+    }
+    let phrase = tokens.join(" ");
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let mut per_user: Vec<(i64, i64)> = if tokens.len() <= MAX_NGRAM {
+        conn.query(
+            include_sql!("phrases/getngramusers.sql"),
+            params![chatid, tokens.len() as i16, phrase, topic],
+        )
+        .await
+        .map_err(|e| format!("getting phrase uses: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect()
+    } else {
+        //Too long for the n-gram tables to keep track of, so go through every message instead
+        let messages: Vec<(i64, String)> = conn
+            .query(include_sql!("getmessagebyuser.sql"), params![chatid, topic])
+            .await
+            .map_err(|e| format!("getting messages and userid: {:?}", e))?
+            .into_iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+
+        let mut counts: HashMap<i64, i64> = HashMap::new();
+        for (userid, message) in messages {
+            let uses = tokenize(&message)
+                .windows(tokens.len())
+                .filter(|w| *w == tokens.as_slice())
+                .count() as i64;
+            if uses > 0 {
+                *counts.entry(userid).or_insert(0) += uses;
+            }
+        }
+        counts.into_iter().collect()
+    };
+    per_user.sort_unstable_by_key(|(_, uses)| -uses);
+
+    let total: i64 = per_user.iter().map(|(_, uses)| uses).sum();
+    if total == 0 {
+        reply!(format!("I have never seen \"{}\"", phrase));
+    }
+
+    let mut redis = context.redis_pool.get().await;
+    let mut table = String::new();
+    for (userid, uses) in per_user.into_iter().take(PHRASE_USERS_SHOWN) {
+        table += &format!(
+            "{}: {}\n",
+            get_user(chatid, userid, telegram, context, &mut redis).await,
+            uses
+        );
+    }
+
+    telegram
+        .send_message_silently_with_markdown(
+            msg,
+            format!(
+                "```\nI have seen \"{}\" {} time(s).\n{}```",
+                phrase,
+                total,
+                align_text_after(':', table)
+            ),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending phrase count: {}", e))
+}
@@ -0,0 +1,190 @@
+use crate::{
+    telegram::{
+        callbackquery::CallbackQuery,
+        message::{Message, MessageData},
+        Telegram,
+    },
+    util::{escape_html, find_user, get_users, UserMatch, USER_ID_PREFIX},
+    Context,
+};
+use serde::{Deserialize, Serialize};
+
+//How long the users of an ambiguous command can be picked from, in seconds
+const PICK_EXPIRY: u32 = 3600 * 24;
+const USERS_OFFERED: usize = 8;
+
+//A command waiting for its sender to pick which user they meant
+#[derive(Serialize, Deserialize)]
+struct PendingCommand {
+    command_message_id: i64,
+    senderid: i64,
+    text: String,
+    name: String,
+    //Which word of the text the name starts at, with the command being the first
+    argument: usize,
+}
+
+//Finds the user a command argument refers to. Returns None after telling the sender when
+//nobody matches, or after asking them which user they meant when several do. The argument is
+//where the name is in the command, so the picked user can replace it.
+pub async fn resolve_user(
+    msg: &Message,
+    name: &str,
+    argument: usize,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<Option<i64>, String> {
+    let candidates = match find_user(msg.chat.id, name, &context.db_pool).await {
+        UserMatch::Found(u) => return Ok(Some(u)),
+        UserMatch::Ambiguous(c) => c,
+        UserMatch::NotFound => {
+            return telegram
+                .send_message_silent(msg, format!("I haven't seen {} yet", name))
+                .await
+                .map(|_| None)
+                .map_err(|e| format!("sending invalid user message: {}", e))
+        }
+    };
+    //Commands are only ever sent as text
+    let text = match msg.data {
+        MessageData::Text(ref t) => t.clone(),
+        _ => return Ok(None),
+    };
+
+    let mut redis = context.redis_pool.get().await;
+    let candidates = &candidates[..candidates.len().min(USERS_OFFERED)];
+    let users = get_users(msg.chat.id, candidates, telegram, context, &mut redis).await;
+    let buttons: Vec<serde_json::Value> = candidates
+        .iter()
+        .map(|id| {
+            serde_json::json!([{
+                "text": users[id].to_string(),
+                "callback_data": format!("pickuser:{}", id),
+            }])
+        })
+        .collect();
+
+    let sent = telegram
+        .reply_with_markup(
+            msg.id,
+            msg,
+            format!("Which {} do you mean?", name),
+            serde_json::json!({ "inline_keyboard": buttons }),
+        )
+        .await
+        .map_err(|e| format!("sending pick user message: {}", e))?;
+
+    let pending = PendingCommand {
+        command_message_id: msg.id,
+        senderid: msg.from.id,
+        text,
+        name: name.to_string(),
+        argument,
+    };
+    let key = format!("tg.pickuser.{}.{}", msg.chat.id, sent.id);
+    redis
+        .set_and_expire_seconds(&key, rmp_serde::to_vec(&pending).unwrap(), PICK_EXPIRY)
+        .await
+        .map_err(|e| format!("storing command waiting for a user: {:?}", e))?;
+    Ok(None)
+}
+
+//Byte ranges of the words in text, split the same way commands are
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => (),
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.len()));
+    }
+    spans
+}
+
+//Runs the command again with the picked user in place of the ambiguous name
+pub async fn handle_callback(
+    callback: &CallbackQuery,
+    userid: &str,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let message = match callback.message {
+        Some(ref m) => m,
+        None => {
+            return telegram
+                .answer_callback_query(&callback.id, Some("This command is too old".into()))
+                .await
+        }
+    };
+    let userid: i64 = userid
+        .parse()
+        .map_err(|e| format!("parsing picked user '{}': {}", userid, e))?;
+
+    let mut redis = context.redis_pool.get().await;
+    let key = format!("tg.pickuser.{}.{}", message.chat.id, message.id);
+    let pending: PendingCommand = match redis
+        .get(&key)
+        .await
+        .map_err(|e| format!("getting command waiting for a user: {:?}", e))?
+    {
+        Some(p) => rmp_serde::from_slice(&p).unwrap(),
+        None => {
+            return telegram
+                .answer_callback_query(&callback.id, Some("This command has expired".into()))
+                .await
+        }
+    };
+    if pending.senderid != callback.from.id {
+        return telegram
+            .answer_callback_query(
+                &callback.id,
+                Some("Only the one who sent the command can pick".into()),
+            )
+            .await;
+    }
+    redis
+        .del(&key)
+        .await
+        .map_err(|e| format!("removing command waiting for a user: {:?}", e))?;
+
+    //Names can span several words. Only they are replaced so the rest of the text stays as is.
+    let words = word_spans(&pending.text);
+    let name_words = pending.name.split_whitespace().count().max(1);
+    let mut text = pending.text.clone();
+    if let (Some((start, _)), Some((_, end))) = (
+        words.get(pending.argument),
+        words.get(pending.argument + name_words - 1),
+    ) {
+        text.replace_range(start..end, &format!("{}{}", USER_ID_PREFIX, userid));
+    }
+
+    let users = get_users(message.chat.id, &[userid], telegram, context, &mut redis).await;
+    telegram
+        .edit_message_with_html(
+            message.chat.id,
+            message.id,
+            format!("Picked {}", escape_html(&users[&userid].to_string())),
+            None,
+        )
+        .await
+        .map_err(|e| format!("editing pick user message: {}", e))?;
+    telegram.answer_callback_query(&callback.id, None).await?;
+
+    //Answer the command as if it was sent with the user picked
+    let command = Message {
+        id: pending.command_message_id,
+        from: callback.from.clone(),
+        data: MessageData::Text(text.clone()),
+        ..message.clone()
+    };
+    super::handle_command(&command, &text, telegram, context).await;
+    Ok(())
+}
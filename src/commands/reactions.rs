@@ -0,0 +1,160 @@
+use crate::{
+    include_sql, params,
+    telegram::{message::Message, Telegram},
+    util::{escape_html, get_user, message_link, topic_scope, Arguments},
+    Context,
+};
+
+const MESSAGES_SHOWN: i64 = 5;
+const USERS_SHOWN: i64 = 5;
+const FAVOURITES_SHOWN: usize = 10;
+//Longest bit of a message quoted in the list of most reacted messages, in characters
+const SNIPPET_LENGTH: usize = 60;
+
+//Turns a stored reaction back into something which can be shown
+fn describe(reaction: &str) -> &str {
+    if reaction.starts_with("custom:") {
+        "a custom emoji"
+    } else if reaction == "paid" {
+        "⭐"
+    } else {
+        reaction
+    }
+}
+
+const USAGE: &str = "Usage: /reactions [--topic [<name>]]";
+
+pub async fn reactions(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending reactions error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, USAGE));
+    }
+    if !arguments.positional.is_empty() {
+        reply!(USAGE.to_string());
+    }
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let mut redis = context.redis_pool.get().await;
+
+    let top = conn
+        .query(
+            include_sql!("reactions/gettopmessages.sql"),
+            params![chatid, MESSAGES_SHOWN, topic],
+        )
+        .await
+        .map_err(|e| format!("getting most reacted messages: {:?}", e))?;
+    if top.is_empty() {
+        reply!("I haven't seen any reactions yet".into());
+    }
+
+    let mut output = "<b>Most reacted messages</b>\n".to_string();
+    for (index, row) in top.into_iter().enumerate() {
+        let msgid: i64 = row.get(0);
+        let total: i64 = row.get(1);
+        //Only text messages have something to quote
+        let quote = match (
+            row.get::<_, Option<i64>>(2),
+            row.get::<_, Option<String>>(3),
+        ) {
+            (Some(userid), Some(text)) => {
+                let mut snippet: String = text.chars().take(SNIPPET_LENGTH).collect();
+                if snippet.len() < text.len() {
+                    snippet += "…";
+                }
+                format!(
+                    "{}: {}",
+                    escape_html(
+                        &get_user(chatid, userid, telegram, context, &mut redis)
+                            .await
+                            .to_string()
+                    ),
+                    escape_html(&snippet)
+                )
+            }
+            _ => "A message".to_string(),
+        };
+        output += &format!("{}. {} ({} reactions)", index + 1, quote, total);
+        if let Some(link) = message_link(&msg.chat, msgid) {
+            output += &format!(" <a href=\"{}\">Go to message</a>", link);
+        }
+        output += "\n";
+    }
+
+    for (title, sql) in [
+        (
+            "Most reactions given",
+            include_sql!("reactions/gettopgivers.sql"),
+        ),
+        (
+            "Most reactions received",
+            include_sql!("reactions/gettopreceivers.sql"),
+        ),
+    ] {
+        let rows = conn
+            .query(sql, params![chatid, USERS_SHOWN, topic])
+            .await
+            .map_err(|e| format!("getting reaction leaderboard: {:?}", e))?;
+        if rows.is_empty() {
+            continue;
+        }
+        output += &format!("\n<b>{}</b>\n", title);
+        for row in rows {
+            let user = get_user(chatid, row.get(0), telegram, context, &mut redis).await;
+            output += &format!(
+                "{}: {}\n",
+                escape_html(&user.to_string()),
+                row.get::<_, i64>(1)
+            );
+        }
+    }
+
+    let mut favourites = conn
+        .query(
+            include_sql!("reactions/getfavourites.sql"),
+            params![chatid, topic],
+        )
+        .await
+        .map_err(|e| format!("getting favourite reactions: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect::<Vec<(i64, String, i64)>>();
+    favourites.sort_by_key(|(_, _, uses)| -uses);
+    if !favourites.is_empty() {
+        output += "\n<b>Favourite reactions</b>\n";
+        for (userid, reaction, uses) in favourites.into_iter().take(FAVOURITES_SHOWN) {
+            let user = get_user(chatid, userid, telegram, context, &mut redis).await;
+            output += &format!(
+                "{}: {} ({})\n",
+                escape_html(&user.to_string()),
+                escape_html(describe(&reaction)),
+                uses
+            );
+        }
+    }
+
+    telegram
+        .send_message_silently_with_html(msg, output, None)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending reaction statistics: {}", e))
+}
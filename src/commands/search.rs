@@ -0,0 +1,237 @@
+use super::pickuser::resolve_user;
+use crate::{
+    include_sql, params,
+    telegram::{callbackquery::CallbackQuery, chat::Chat, message::Message, Telegram},
+    util::{escape_html, get_user, message_link, page_buttons, parse_time, topic_scope, Arguments},
+    Context,
+};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const RESULTS_PER_PAGE: i64 = 5;
+//How long a search can be paged through, in seconds
+const SEARCH_EXPIRY: u32 = 3600 * 24;
+//Private use characters marking matches in the snippets, as they have to be escaped first
+const HIGHLIGHT_START: char = '\u{E000}';
+const HIGHLIGHT_STOP: char = '\u{E001}';
+
+const USAGE: &str =
+    "Usage: /search <terms> [--user <name>] [--since <n> <units>] [--topic [<name>]]";
+
+#[derive(Serialize, Deserialize)]
+struct SearchQuery {
+    terms: String,
+    userid: Option<i64>,
+    since: i64,
+    #[serde(default)]
+    topic: Option<i64>,
+}
+
+//Renders a page of results, returning the text and the total number of pages
+async fn render_page(
+    chat: &Chat,
+    query: &SearchQuery,
+    page: i64,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<Option<(String, i64)>, String> {
+    let options = format!(
+        "StartSel={}, StopSel={}, MaxWords=30, MinWords=10, MaxFragments=2",
+        HIGHLIGHT_START, HIGHLIGHT_STOP
+    );
+    let conn = context.db_pool.get().await.unwrap();
+    let results = conn
+        .query(
+            include_sql!("search/search.sql"),
+            params![
+                chat.id,
+                query.terms,
+                query.userid,
+                query.since,
+                RESULTS_PER_PAGE,
+                page * RESULTS_PER_PAGE,
+                options,
+                query.topic
+            ],
+        )
+        .await
+        .map_err(|e| format!("searching messages: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4)))
+        .collect::<Vec<(i64, i64, i64, String, i64)>>();
+
+    let total = match results.first() {
+        Some(r) => r.4,
+        None => return Ok(None),
+    };
+    let pages = (total + RESULTS_PER_PAGE - 1) / RESULTS_PER_PAGE;
+
+    let first = page * RESULTS_PER_PAGE + 1;
+    let mut output = format!(
+        "Results {}-{} of {} for \"{}\":\n",
+        first,
+        first + results.len() as i64 - 1,
+        total,
+        escape_html(&query.terms)
+    );
+
+    let mut redis = context.redis_pool.get().await;
+    for (index, (userid, msgid, instant, snippet, _)) in results.into_iter().enumerate() {
+        let author = get_user(chat.id, userid, telegram, context, &mut redis).await;
+        let date = Local
+            .timestamp_opt(instant, 0)
+            .unwrap()
+            .format(&context.config.general.time_format);
+        let snippet = escape_html(&snippet)
+            .replace(HIGHLIGHT_START, "<b>")
+            .replace(HIGHLIGHT_STOP, "</b>");
+
+        output += &format!(
+            "\n<b>{}.</b> {}, <i>{}</i>\n{}\n",
+            first + index as i64,
+            escape_html(&author.to_string()),
+            date,
+            snippet
+        );
+        if let Some(link) = message_link(chat, msgid) {
+            output += &format!("<a href=\"{}\">Go to message</a>\n", link);
+        }
+    }
+
+    Ok(Some((output, pages)))
+}
+
+pub async fn search(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending search error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["user", "since", "topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, USAGE));
+    }
+    if arguments.positional.is_empty() {
+        reply!(USAGE.to_string());
+    }
+
+    let userid = match arguments.flag("user") {
+        //The command itself comes before the arguments which were parsed
+        Some([name]) => {
+            let argument = 1 + arguments.flag_position("user").unwrap();
+            match resolve_user(msg, name, argument, telegram, context).await? {
+                Some(u) => Some(u),
+                None => return Ok(()),
+            }
+        }
+        Some(_) => reply!(USAGE.to_string()),
+        None => None,
+    };
+
+    let since = match arguments.flag("since") {
+        Some(time) => match parse_time(time) {
+            Some(t) => (Utc::now() - t).timestamp(),
+            None => reply!("Invalid time string".to_string()),
+        },
+        None => 0,
+    };
+
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let query = SearchQuery {
+        terms: arguments.positional.join(" "),
+        userid,
+        since,
+        topic,
+    };
+
+    let (text, pages) = match render_page(&msg.chat, &query, 0, telegram, context).await? {
+        Some(r) => r,
+        None => reply!(format!("I found no messages matching \"{}\"", query.terms)),
+    };
+
+    let markup = if pages > 1 {
+        Some(page_buttons("search", 0, pages))
+    } else {
+        None
+    };
+    let sent = telegram
+        .send_message_silently_with_html(msg, text, markup)
+        .await
+        .map_err(|e| format!("sending search results: {}", e))?;
+
+    if pages > 1 {
+        let mut redis = context.redis_pool.get().await;
+        let key = format!("tg.search.{}.{}", chatid, sent.id);
+        redis
+            .set_and_expire_seconds(&key, rmp_serde::to_vec(&query).unwrap(), SEARCH_EXPIRY)
+            .await
+            .map_err(|e| format!("storing search query: {:?}", e))?;
+    }
+
+    Ok(())
+}
+
+//Flips to another page of results when one of the page buttons is pressed
+pub async fn handle_callback(
+    callback: &CallbackQuery,
+    page: &str,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let message = match callback.message {
+        Some(ref m) => m,
+        None => {
+            return telegram
+                .answer_callback_query(&callback.id, Some("This search is too old".into()))
+                .await
+        }
+    };
+    let page: i64 = page
+        .parse()
+        .map_err(|e| format!("parsing search page '{}': {}", page, e))?;
+
+    let mut redis = context.redis_pool.get().await;
+    let key = format!("tg.search.{}.{}", message.chat.id, message.id);
+    let query: SearchQuery = match redis
+        .get(&key)
+        .await
+        .map_err(|e| format!("getting search query: {:?}", e))?
+    {
+        Some(q) => rmp_serde::from_slice(&q).unwrap(),
+        None => {
+            return telegram
+                .answer_callback_query(&callback.id, Some("This search has expired".into()))
+                .await
+        }
+    };
+
+    if let Some((text, pages)) = render_page(&message.chat, &query, page, telegram, context).await?
+    {
+        telegram
+            .edit_message_with_html(
+                message.chat.id,
+                message.id,
+                text,
+                Some(page_buttons("search", page, pages)),
+            )
+            .await
+            .map_err(|e| format!("editing search results: {}", e))?;
+    }
+
+    telegram.answer_callback_query(&callback.id, None).await
+}
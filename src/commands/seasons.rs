@@ -0,0 +1,303 @@
+use super::{
+    categories::{self, DEFAULT_CATEGORY},
+    settings,
+};
+use crate::{
+    include_sql, params, render,
+    telegram::{message::Message, ChatTarget, Telegram},
+    util::{align_text_after, get_users},
+    Context,
+};
+use chrono::prelude::*;
+use deadpool_postgres::Client;
+use tokio::task;
+
+//How often chats with monthly seasons are checked for a new month, in seconds
+const TICK_INTERVAL: u64 = 15 * 60;
+const ALL_TIME_SHOWN: i64 = 10;
+
+pub struct Season {
+    pub number: i64,
+    pub started: i64,
+    //None for the current season
+    pub ended: Option<i64>,
+}
+
+pub async fn current_season(chatid: i64, conn: &Client) -> Result<Season, String> {
+    let current = conn
+        .query_opt(include_sql!("seasons/getcurrent.sql"), params![chatid])
+        .await
+        .map_err(|e| format!("getting current season: {:?}", e))?;
+    Ok(match current {
+        Some(row) => Season {
+            number: row.get(0),
+            started: row.get(1),
+            ended: None,
+        },
+        //Chats which never had a season end are still in the first one
+        None => Season {
+            number: 1,
+            started: 0,
+            ended: None,
+        },
+    })
+}
+
+pub async fn get_season(chatid: i64, number: i64, conn: &Client) -> Result<Option<Season>, String> {
+    let current = current_season(chatid, conn).await?;
+    if number == current.number {
+        return Ok(Some(current));
+    }
+    conn.query_opt(include_sql!("seasons/get.sql"), params![chatid, number])
+        .await
+        .map(|row| {
+            row.map(|r| Season {
+                number,
+                started: r.get(0),
+                ended: r.get(1),
+            })
+        })
+        .map_err(|e| format!("getting season {}: {:?}", number, e))
+}
+
+//Archives the standings of the current season, starts the next one and crowns the champion
+async fn end_season(
+    chat: ChatTarget,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = chat.chat_id;
+    let conn = context.db_pool.get().await.unwrap();
+    let season = current_season(chatid, &conn).await?;
+    conn.execute(
+        include_sql!("seasons/end.sql"),
+        params![
+            chatid,
+            season.number,
+            Utc::now().timestamp(),
+            season.started
+        ],
+    )
+    .await
+    .map_err(|e| format!("ending season {}: {:?}", season.number, e))?;
+    info!("Season {} of chat {} ended", season.number, chatid);
+
+    let standings = conn
+        .query(
+            include_sql!("seasons/getstandings.sql"),
+            params![chatid, season.number, None::<String>],
+        )
+        .await
+        .map_err(|e| format!("getting season standings: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect::<Vec<(i64, i64, String)>>();
+
+    if standings.is_empty() {
+        return telegram
+            .send_message_silent(
+                chat,
+                format!(
+                    "Season {} is over without any points given. Season {} starts now!",
+                    season.number,
+                    season.number + 1
+                ),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending empty season message: {}", e));
+    }
+
+    let mut redis = context.redis_pool.get().await;
+    let ids: Vec<i64> = standings.iter().map(|s| s.1).collect();
+    let names = get_users(chatid, &ids, telegram, context, &mut redis).await;
+
+    //Standings are sorted by category, so each category gets a podium of its own
+    let categories: Vec<&str> =
+        standings
+            .iter()
+            .map(|s| s.2.as_str())
+            .fold(Vec::new(), |mut categories, c| {
+                if categories.last() != Some(&c) {
+                    categories.push(c);
+                }
+                categories
+            });
+    for (index, category) in categories.iter().enumerate() {
+        let ranked: Vec<&(i64, i64, String)> =
+            standings.iter().filter(|s| s.2 == *category).collect();
+        let places: Vec<(String, i64)> = ranked
+            .iter()
+            .take(3)
+            .map(|(points, userid, _)| (names[userid].to_string(), *points))
+            .collect();
+        let title = format!("Season {}: {}", season.number, category);
+        let image = task::block_in_place(|| render::podium(&title, &places))?;
+
+        let (points, winner, _) = ranked[0];
+        let mut caption = if *category == DEFAULT_CATEGORY {
+            format!(
+                "{} is the biggest disaster with {} points.",
+                names[winner], points
+            )
+        } else {
+            format!(
+                "{} has the most {} points with {}.",
+                names[winner], category, points
+            )
+        };
+        if index == 0 {
+            caption = format!("Season {} is over! {}", season.number, caption);
+        }
+        if index == categories.len() - 1 {
+            caption += &format!(" Season {} starts now!", season.number + 1);
+        }
+        telegram
+            .send_png_lossless(chat, image, Some(caption), false)
+            .await
+            .map_err(|e| format!("sending season podium: {}", e))?;
+    }
+    Ok(())
+}
+
+//Usage: /newseason
+pub async fn new_season(
+    msg: &Message,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    if !settings::is_admin(msg, telegram).await {
+        return telegram
+            .reply_to(
+                msg.id,
+                msg,
+                "Only chat administrators can start a new season".into(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending not an admin message: {}", e));
+    }
+    end_season(msg.into(), telegram, context).await
+}
+
+//Usage: /halloffame [<category>]
+pub async fn hall_of_fame(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let category = args.get(1).map_or(DEFAULT_CATEGORY, |c| c.as_str());
+    let category = match categories::find(chatid, category, context).await? {
+        Some(c) => c.name,
+        None => {
+            return telegram
+                .send_message_silent(msg, format!("There is no category named {}", category))
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending unknown category message: {}", e))
+        }
+    };
+    let conn = context.db_pool.get().await.unwrap();
+    let champions = conn
+        .query(
+            include_sql!("seasons/getchampions.sql"),
+            params![chatid, category],
+        )
+        .await
+        .map_err(|e| format!("getting season champions: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect::<Vec<(i64, i64, i64)>>();
+    let all_time = conn
+        .query(
+            include_sql!("seasons/getalltime.sql"),
+            params![chatid, ALL_TIME_SHOWN, category],
+        )
+        .await
+        .map_err(|e| format!("getting all-time points: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect::<Vec<(i64, i64)>>();
+
+    if champions.is_empty() && all_time.is_empty() {
+        return telegram
+            .send_message_silent(msg, format!("No {} points have been given yet", category))
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending no points message: {}", e));
+    }
+
+    let mut redis = context.redis_pool.get().await;
+    let ids: Vec<i64> = champions
+        .iter()
+        .map(|c| c.1)
+        .chain(all_time.iter().map(|a| a.0))
+        .collect();
+    let names = get_users(chatid, &ids, telegram, context, &mut redis).await;
+
+    let mut output = "```\n".to_string();
+    if !champions.is_empty() {
+        output += "Champions:\n";
+        let mut table = String::new();
+        for (season, userid, points) in champions {
+            table += &format!("Season {}: {} ({})\n", season, names[&userid], points);
+        }
+        output += &align_text_after(':', table);
+        output += "\n";
+    }
+    output += &format!("All-time {} points:\n", category);
+    let mut table = String::new();
+    for (userid, points) in all_time {
+        table += &format!("{}: {}\n", names[&userid], points);
+    }
+    output += &align_text_after(':', table);
+
+    telegram
+        .send_message_silently_with_markdown(msg, format!("{}```", output))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending hall of fame: {}", e))
+}
+
+//Ends the seasons of chats with monthly seasons once a new month has begun
+async fn end_monthly_seasons(telegram: &Telegram, context: &Context) -> Result<(), String> {
+    let now = Local::now();
+    let month_start = Local
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .earliest()
+        .map(|t| t.timestamp())
+        .unwrap_or(0);
+
+    let chats = {
+        let conn = context.db_pool.get().await.unwrap();
+        conn.query(include_sql!("seasons/getmonthly.sql"), &[])
+            .await
+            .map_err(|e| format!("getting chats with monthly seasons: {:?}", e))?
+            .into_iter()
+            .map(|row| (row.get(0), row.get(1), row.get(2)))
+            .collect::<Vec<(i64, Option<i64>, i64)>>()
+    };
+
+    for (chatid, started, monthly_since) in chats {
+        //The first season has no row until it ends and has been going on since the beginning, so
+        //a season only counts as started once monthly seasons were turned on
+        if started.unwrap_or(0).max(monthly_since) < month_start {
+            if let Err(e) = end_season(chatid.into(), telegram, context).await {
+                error!("Ending the season of {} failed at '{}'", chatid, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn ticker(telegram: &Telegram, context: &Context) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(TICK_INTERVAL));
+    loop {
+        interval.tick().await;
+        if let Err(e) = end_monthly_seasons(telegram, context).await {
+            error!("Checking for monthly seasons failed at '{}'", e);
+        }
+    }
+}
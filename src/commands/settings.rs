@@ -0,0 +1,311 @@
+use crate::{
+    include_sql, params,
+    telegram::{chat::ChatType, message::Message, Telegram},
+    tokenizer::{stopwords, StopWords},
+    Context,
+};
+
+//Longest a setting in minutes can be, a week
+const MAX_MINUTES: u32 = 7 * 24 * 60;
+//Longest a setting in hours can be, a year
+const MAX_HOURS: u32 = 365 * 24;
+
+//A per-chat setting which chat admins can change with /settings
+struct Setting {
+    name: &'static str,
+    description: &'static str,
+    default: &'static str,
+    //Validates a new value and returns it in the form it should be stored as
+    parse: fn(&str) -> Result<String, String>,
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        name: "stopwords",
+        description: "Comma separated languages whose stop words are left out of word statistics",
+        default: "",
+        parse: parse_stopwords,
+    },
+    Setting {
+        name: "repostwindow",
+        description: "Days within which a link being posted again gets pointed out, 0 to never",
+        default: "0",
+        parse: parse_days,
+    },
+    Setting {
+        name: "oldwindow",
+        description:
+            "Days within which a photo being posted again gets an \"old!\" reply, 0 to never",
+        default: "0",
+        parse: parse_days,
+    },
+    Setting {
+        name: "seasons",
+        description: "How disaster point seasons end, either manual with /newseason or monthly",
+        default: "manual",
+        parse: parse_seasons,
+    },
+    Setting {
+        name: "disastervotes",
+        description: "Members who have to confirm a disaster point before it's given, 0 to not ask",
+        default: "0",
+        parse: parse_count,
+    },
+    Setting {
+        name: "disastervotetime",
+        description: "Minutes members have to confirm a disaster point, at most a week",
+        default: "60",
+        parse: parse_minutes,
+    },
+    Setting {
+        name: "pointminmessages",
+        description: "Messages a member has to have sent before they can give points",
+        default: "0",
+        parse: parse_count,
+    },
+    Setting {
+        name: "pointreceivercooldown",
+        description: "Hours before someone who got a point can get another of the same kind, 0 to not wait, at most a year",
+        default: "0",
+        parse: parse_hours,
+    },
+    Setting {
+        name: "pointpairlimit",
+        description: "Points of a kind one member can give the same member within pointpairdays, 0 for no limit",
+        default: "0",
+        parse: parse_count,
+    },
+    Setting {
+        name: "pointpairdays",
+        description: "Days over which pointpairlimit counts points, 0 for all time",
+        default: "7",
+        parse: parse_days,
+    },
+];
+
+fn parse_stopwords(value: &str) -> Result<String, String> {
+    if value == "none" {
+        return Ok(String::new());
+    }
+
+    let mut languages: Vec<String> = Vec::new();
+    for language in value.split(',').map(|l| l.trim().to_lowercase()) {
+        if language.is_empty() || languages.contains(&language) {
+            continue;
+        }
+        if !stopwords::is_supported(&language) {
+            return Err(format!(
+                "Unknown language '{}', available languages are: {}",
+                language,
+                stopwords::languages().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        languages.push(language);
+    }
+    Ok(languages.join(","))
+}
+
+fn parse_days(value: &str) -> Result<String, String> {
+    value
+        .parse::<u32>()
+        .map(|days| days.to_string())
+        .map_err(|_| format!("'{}' is not a whole number of days", value))
+}
+
+fn parse_hours(value: &str) -> Result<String, String> {
+    match value.parse::<u32>() {
+        Ok(hours) if hours > MAX_HOURS => Err(format!("{} hours is longer than a year", hours)),
+        Ok(hours) => Ok(hours.to_string()),
+        Err(_) => Err(format!("'{}' is not a whole number of hours", value)),
+    }
+}
+
+fn parse_count(value: &str) -> Result<String, String> {
+    value
+        .parse::<u32>()
+        .map(|count| count.to_string())
+        .map_err(|_| format!("'{}' is not a whole number", value))
+}
+
+fn parse_minutes(value: &str) -> Result<String, String> {
+    match value.parse::<u32>() {
+        Ok(minutes) if minutes > MAX_MINUTES => {
+            Err(format!("{} minutes is longer than a week", minutes))
+        }
+        Ok(minutes) if minutes > 0 => Ok(minutes.to_string()),
+        _ => Err(format!("'{}' is not a positive number of minutes", value)),
+    }
+}
+
+fn parse_seasons(value: &str) -> Result<String, String> {
+    match value.to_lowercase().as_str() {
+        v @ ("manual" | "monthly") => Ok(v.to_string()),
+        _ => Err(format!(
+            "'{}' isn't a kind of season, use manual or monthly",
+            value
+        )),
+    }
+}
+
+fn find_setting(name: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|s| s.name == name)
+}
+
+//Gets the value of a setting in a chat, falling back to its default value
+pub async fn get(chatid: i64, name: &str, context: &Context) -> Result<String, String> {
+    let setting = find_setting(name).expect("requested setting doesn't exist");
+    let conn = context.db_pool.get().await.unwrap();
+    conn.query_opt(include_sql!("settings/get.sql"), params![chatid, name])
+        .await
+        .map(|row| {
+            row.map(|r| r.get(0))
+                .unwrap_or_else(|| setting.default.to_string())
+        })
+        .map_err(|e| format!("getting setting {}: {:?}", name, e))
+}
+
+pub async fn stop_words(chatid: i64, context: &Context) -> Result<StopWords, String> {
+    let languages = get(chatid, "stopwords", context).await?;
+    Ok(StopWords::for_languages(languages.split(',')))
+}
+
+pub async fn is_admin(msg: &Message, telegram: &Telegram) -> bool {
+    if let ChatType::Private = msg.chat.kind {
+        return true;
+    }
+
+    match telegram.get_chat_member(msg.chat.id, msg.from.id).await {
+        Ok(member) => member.status.is_admin(),
+        Err(e) => {
+            error!(
+                "Couldn't get member status of {} in {}: {:?}",
+                msg.from.id, msg.chat.id, e
+            );
+            false
+        }
+    }
+}
+
+fn describe(setting: &Setting, value: &str) -> String {
+    format!(
+        "{} = \"{}\"{}\n    {}\n",
+        setting.name,
+        value,
+        if value == setting.default {
+            " (default)"
+        } else {
+            ""
+        },
+        setting.description
+    )
+}
+
+//Usage: /settings [<name> [<value>|reset]]
+pub async fn settings(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    if args.len() == 1 {
+        let conn = context.db_pool.get().await.unwrap();
+        let values = conn
+            .query(include_sql!("settings/getall.sql"), params![chatid])
+            .await
+            .map_err(|e| format!("getting chat settings: {:?}", e))?
+            .into_iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect::<Vec<(String, String)>>();
+
+        let mut output = "Settings for this chat:\n".to_string();
+        for setting in SETTINGS {
+            let value = values
+                .iter()
+                .find(|(name, _)| name == setting.name)
+                .map(|(_, value)| value.as_str())
+                .unwrap_or(setting.default);
+            output += &describe(setting, value);
+        }
+        output += "\nChange one with /settings <name> <value>, or /settings <name> reset";
+
+        return telegram
+            .send_message_silent(msg, output)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending settings list: {}", e));
+    }
+
+    let setting = match find_setting(&args[1]) {
+        Some(s) => s,
+        None => {
+            return telegram
+                .send_message_silent(msg, format!("There is no setting named {}", args[1]))
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending no such setting message: {}", e));
+        }
+    };
+
+    if args.len() == 2 {
+        let value = get(chatid, setting.name, context).await?;
+        return telegram
+            .send_message_silent(msg, describe(setting, &value))
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending setting value: {}", e));
+    }
+
+    if !is_admin(msg, telegram).await {
+        return telegram
+            .reply_to(
+                msg.id,
+                msg,
+                "Only chat administrators can change settings".into(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending not an admin message: {}", e));
+    }
+
+    let conn = context.db_pool.get().await.unwrap();
+    let input = args[2..].join(" ");
+    let value = if input == "reset" {
+        conn.execute(
+            include_sql!("settings/reset.sql"),
+            params![chatid, setting.name],
+        )
+        .await
+        .map_err(|e| format!("resetting setting {}: {:?}", setting.name, e))?;
+        setting.default.to_string()
+    } else {
+        match (setting.parse)(&input) {
+            Ok(value) => {
+                conn.execute(
+                    include_sql!("settings/set.sql"),
+                    params![chatid, setting.name, value, msg.date],
+                )
+                .await
+                .map_err(|e| format!("setting {}: {:?}", setting.name, e))?;
+                value
+            }
+            Err(e) => {
+                return telegram
+                    .reply_to(msg.id, msg, e)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("sending invalid setting message: {}", e));
+            }
+        }
+    };
+
+    info!(
+        "[{}] {} set {} to {}",
+        msg.chat, msg.from, setting.name, value
+    );
+    telegram
+        .reply_to(msg.id, msg, describe(setting, &value))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending new setting value: {}", e))
+}
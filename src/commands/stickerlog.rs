@@ -1,79 +1,85 @@
+use super::pickuser::resolve_user;
 use crate::{
+    image::{decode, hamming_distance, Image, SIMILAR_DISTANCE},
     include_sql, params,
-    telegram::{message::Message, Telegram},
-    util::{parse_time, rgba_to_cairo},
+    telegram::{message::Message, Sticker, StickerSet, Telegram},
+    tokenizer::normalize_emoji,
+    util::{calculate_sticker_hash, get_user, parse_time, rgba_to_cairo, topic_scope, Arguments},
     Context,
 };
 use cairo::Format;
 use chrono::{prelude::*, NaiveDateTime, Utc};
-use libc::c_int;
+use std::collections::HashMap;
 use tokio::task;
 
-struct Image {
-    data: *mut u8,
-    size: usize,
-    width: i32,
-    height: i32,
+const DEFAULT_STICKERS: usize = 25;
+const MAX_STICKERS: usize = 100;
+//Stickers wrap onto a new row after this many
+const STICKERS_PER_ROW: usize = 10;
+
+const USAGE: &str = "Usage: /stickerlog [<n> <units>] [--user <name>] [--pack <set name>] \
+                     [--emoji <emoji>] [--top <n>] [--topic [<name>]]";
+
+//A sticker in the chart along with its image, which is missing when it couldn't be downloaded
+//or decoded, or when an animated sticker was logged before thumbnails were and its pack doesn't
+//have it anymore
+struct ChartSticker {
+    image: Option<Image>,
+    animated: bool,
+    uses: i64,
 }
 
-fn decode_webp(input: &[u8]) -> Result<Image, String> {
-    let mut width: c_int = 0;
-    let mut height: c_int = 0;
-    unsafe {
-        let data = libwebp_sys::WebPDecodeRGBA(
-            input.as_ptr(),
-            input.len(),
-            &mut width as *mut c_int,
-            &mut height as *mut c_int,
-        );
-
-        if data.is_null() {
-            return Err("decoding image as webp".to_string());
-        }
-        let size = width as usize * height as usize * 4; // RGBA
-        Ok(Image {
-            data,
-            size,
-            width,
-            height,
-        })
-    }
-}
-
-fn render_image(stickers_webp: Vec<Vec<u8>>, usages: Vec<i64>) -> Result<Vec<u8>, String> {
-    let decoded = stickers_webp
+fn render_image(stickers: Vec<ChartSticker>) -> Result<Vec<u8>, String> {
+    let (decoded, usages) = stickers
         .into_iter()
-        .map(|s| decode_webp(&s))
-        .collect::<Result<Vec<Image>, String>>()?;
-    unsafe {
-        decoded
-            .iter()
-            .for_each(|image| rgba_to_cairo(image.data, image.size));
-    }
-
-    let retval = render_image_inner(&decoded, usages);
+        .map(|s| ((s.image, s.animated), s.uses))
+        .unzip();
+    render_image_inner(decoded, usages).map_err(|e| format!("Cairo error {:?}\n", e))
+}
 
-    decoded.iter().for_each(|i| unsafe {
-        libwebp_sys::WebPFree(i.data as *mut std::ffi::c_void);
-    });
+//Marks a sticker as animated with a play symbol in its corner
+fn draw_badge(cairo: &cairo::Context, x: f64, y: f64) -> Result<(), cairo::Error> {
+    let radius = 24.0;
+    cairo.arc(
+        x + radius,
+        y + radius,
+        radius,
+        0.0,
+        2.0 * std::f64::consts::PI,
+    );
+    cairo.set_source_rgba(0.0, 0.0, 0.0, 0.7);
+    cairo.fill()?;
 
-    retval.map_err(|e| format!("Cairo error {:?}\n", e))
+    cairo.move_to(x + radius * 0.7, y + radius * 0.5);
+    cairo.line_to(x + radius * 1.55, y + radius);
+    cairo.line_to(x + radius * 0.7, y + radius * 1.5);
+    cairo.close_path();
+    cairo.set_source_rgb(1.0, 1.0, 1.0);
+    cairo.fill()
 }
 
-fn render_image_inner(stickers: &[Image], usages: Vec<i64>) -> Result<Vec<u8>, cairo::Error> {
+fn render_image_inner(
+    stickers: Vec<(Option<Image>, bool)>,
+    usages: Vec<i64>,
+) -> Result<Vec<u8>, cairo::Error> {
     let mut rendered_image = Vec::new();
 
     //Fun constants to play with
-    let height = 1200;
     let padding = 50.0; //padding between bars
     let bar_thickness = 40.0;
     let sticker_thickness = 200.0; // Target sticker thickness
     let max_height = 200.0; //Maximum sticker height
-                            //Offset by a bit to prevent the text from clipping at the right edge
-    let width = usages.len() as i32 * (padding as i32 + sticker_thickness as i32) + 100;
 
-    let y_scale = (f64::from(height) - max_height - (padding * 2.0))
-        / f64::from(*usages.iter().max_by(|x, y| x.cmp(y)).unwrap() as i32);
+    //Wrap into a grid when there are too many stickers for one row. Bars get less room then.
+    let rows = usages.len().div_ceil(STICKERS_PER_ROW);
+    let columns = usages.len().min(STICKERS_PER_ROW);
+    let bar_space = if rows == 1 { 900.0 } else { 450.0 };
+    let row_height = max_height + padding * 2.0 + bar_space;
+    let height = (row_height * rows as f64) as i32;
+    //Offset by a bit to prevent the text from clipping at the right edge
+    let width = columns as i32 * (padding as i32 + sticker_thickness as i32) + 100;
+
+    let y_scale = bar_space / f64::from(*usages.iter().max_by(|x, y| x.cmp(y)).unwrap() as i32);
 
     let surface = cairo::ImageSurface::create(Format::ARgb32, width, height)?;
     let cairo = cairo::Context::new(&surface)?;
@@ -89,32 +95,64 @@ fn render_image_inner(stickers: &[Image], usages: Vec<i64>) -> Result<Vec<u8>, c
     cairo.rectangle(0.0, 0.0, f64::from(width), f64::from(height));
     cairo.fill()?;
 
-    for (index, num) in usages.iter().enumerate() {
-        let image = &stickers[index];
-        let slice = unsafe { std::slice::from_raw_parts_mut(image.data, image.size) };
-
-        let format = Format::ARgb32;
-        let stride = format.stride_for_width(image.width as u32)?;
-        let surface =
-            cairo::ImageSurface::create_for_data(slice, format, image.width, image.height, stride)?;
-
-        let x_offset = index as f64 * (sticker_thickness + padding);
-        //Decrease the sticker size until they match the target thickness or maximum height
-        let scale_factor =
-            (sticker_thickness / f64::from(image.width)).min(max_height / f64::from(image.height));
-
-        //Sticker image itself
-        cairo.scale(scale_factor, scale_factor);
-        cairo.set_source_surface(&surface, x_offset * (1.0 / scale_factor), 0.0)?;
-        cairo.paint()?;
-        surface.finish();
+    for (index, ((image, animated), num)) in stickers.into_iter().zip(usages.iter()).enumerate() {
+        let x_offset = (index % STICKERS_PER_ROW) as f64 * (sticker_thickness + padding);
+        let y_offset = (index / STICKERS_PER_ROW) as f64 * row_height;
+        let image_width = match image {
+            Some(image) => {
+                let format = Format::ARgb32;
+                let stride = format.stride_for_width(image.width as u32)?;
+                let surface = cairo::ImageSurface::create_for_data(
+                    image.data,
+                    format,
+                    image.width,
+                    image.height,
+                    stride,
+                )?;
+
+                //Decrease the sticker size until they match the target thickness or maximum height
+                let scale_factor = (sticker_thickness / f64::from(image.width))
+                    .min(max_height / f64::from(image.height));
+
+                //Sticker image itself
+                cairo.save()?;
+                cairo.scale(scale_factor, scale_factor);
+                cairo.set_source_surface(
+                    &surface,
+                    x_offset * (1.0 / scale_factor),
+                    y_offset * (1.0 / scale_factor),
+                )?;
+                cairo.paint()?;
+                cairo.restore()?;
+                surface.finish();
+                f64::from(image.width) * scale_factor
+            }
+            None => {
+                cairo.rectangle(x_offset, y_offset, sticker_thickness, max_height);
+                cairo.set_source_rgb(0.3, 0.3, 0.3);
+                cairo.fill()?;
+
+                cairo.select_font_face("Hack", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+                cairo.set_font_size(100.0);
+                let extents = cairo.text_extents("?")?;
+                cairo.move_to(
+                    x_offset + (sticker_thickness - extents.width()) / 2.0 - extents.x_bearing(),
+                    y_offset + (max_height - extents.height()) / 2.0 - extents.y_bearing(),
+                );
+                cairo.set_source_rgb(0.6, 0.6, 0.6);
+                cairo.show_text("?")?;
+                sticker_thickness
+            }
+        };
+        if animated {
+            draw_badge(&cairo, x_offset + 5.0, y_offset + 5.0)?;
+        }
 
         //Bar graphs
-        let normalized_x_offset = x_offset + 0.5 * f64::from(image.height) * scale_factor; //Middle of sticker
-        cairo.scale(1.0 / scale_factor, 1.0 / scale_factor); //undo scaling
+        let normalized_x_offset = x_offset + 0.5 * image_width; //Middle of sticker
         cairo.rectangle(
             normalized_x_offset,
-            max_height + padding,
+            y_offset + max_height + padding,
             bar_thickness,
             f64::from(*num as i32) * y_scale,
         );
@@ -129,7 +167,11 @@ fn render_image_inner(stickers: &[Image], usages: Vec<i64>) -> Result<Vec<u8>, c
         let extents = cairo.text_extents(&num_text)?;
         cairo.move_to(
             normalized_x_offset + extents.width() / 2.0 - extents.x_bearing(),
-            max_height + padding + (f64::from(*num as i32) * y_scale) + extents.height() / 2.0
+            y_offset
+                + max_height
+                + padding
+                + (f64::from(*num as i32) * y_scale)
+                + extents.height() / 2.0
                 - extents.y_bearing(),
         );
         cairo.set_source_rgba(1.0, 1.0, 1.0, 1.0);
@@ -143,20 +185,153 @@ fn render_image_inner(stickers: &[Image], usages: Vec<i64>) -> Result<Vec<u8>, c
     Ok(rendered_image)
 }
 
+struct StickerCount {
+    fileid: String,
+    thumbnailid: Option<String>,
+    animated: bool,
+    phash: Option<i64>,
+    uses: i64,
+    packname: Option<String>,
+    hash: Vec<u8>,
+    emoji: Option<String>,
+}
+
+//Merges stickers which look the same, like copies of a sticker in different packs.
+//The counts should be sorted by uses.
+fn group_similar(counts: Vec<StickerCount>) -> Vec<StickerCount> {
+    let mut groups: Vec<StickerCount> = Vec::new();
+    for count in counts {
+        let similar = count.phash.and_then(|p| {
+            groups.iter_mut().find(|g| {
+                g.phash
+                    .is_some_and(|o| hamming_distance(p, o) <= SIMILAR_DISTANCE)
+            })
+        });
+        match similar {
+            Some(group) => group.uses += count.uses,
+            None => groups.push(count),
+        }
+    }
+    groups.sort_by_key(|g| -g.uses);
+    groups
+}
+
+//Downloads and decodes a sticker image, ready to be drawn with cairo
+async fn download_image(
+    id: &str,
+    telegram: &Telegram,
+    redis: &mut darkredis::Connection,
+) -> Option<Image> {
+    let file = match telegram.download_file(redis, id).await {
+        Ok(f) => f,
+        Err(e) => {
+            warn!("Couldn't download sticker image {}: {}", id, e);
+            return None;
+        }
+    };
+    match task::block_in_place(|| decode(&file)) {
+        Ok(mut image) => {
+            unsafe { rgba_to_cairo(image.data.as_mut_ptr(), image.data.len()) };
+            Some(image)
+        }
+        Err(e) => {
+            info!("Couldn't decode sticker image {}: {}", id, e);
+            None
+        }
+    }
+}
+
+//Finds a logged sticker in its pack, fetching each pack only once. File ids can change over
+//time, so it's found by the logged hash instead.
+async fn pack_sticker<'a>(
+    count: &StickerCount,
+    packs: &'a mut HashMap<String, Option<StickerSet>>,
+    telegram: &Telegram,
+    redis: &mut darkredis::Connection,
+) -> Option<&'a Sticker> {
+    let name = count.packname.as_ref()?;
+    if !packs.contains_key(name) {
+        let set = match telegram.get_sticker_set(name).await {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!("Couldn't get sticker set {}: {}", name, e);
+                None
+            }
+        };
+        packs.insert(name.clone(), set);
+    }
+    let packs: &'a HashMap<_, _> = packs;
+    let emoji = count.emoji.as_deref().unwrap_or("");
+    //The emoji is part of the hash, so only stickers with the same one need to be hashed
+    for sticker in packs[name].as_ref()?.stickers.iter() {
+        if sticker.emoji != count.emoji {
+            continue;
+        }
+        let hash = calculate_sticker_hash(telegram, redis, &sticker.file_id, name, emoji).await;
+        if hash.as_ref() == Ok(&count.hash) {
+            return Some(sticker);
+        }
+    }
+    None
+}
+
 pub async fn stickerlog<'a>(
     msg: &'a Message,
     args: &'a [String],
     telegram: &Telegram,
     context: &Context,
 ) -> Result<(), String> {
-    let (caption, images, usages) = {
-        let parsed_time = parse_time(&args[1..]);
-        if args.len() > 1 && parsed_time.is_none() {
-            telegram
-                .send_message_silent(msg.chat.id, "Invalid time string".to_string())
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
                 .await
-                .map_err(|e| format!("sending error message: {}", e))?;
-            return Ok(());
+                .map(|_| ())
+                .map_err(|e| format!("sending error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["user", "pack", "emoji", "top", "topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, USAGE));
+    }
+    let userid = match arguments.flag("user") {
+        //The command itself comes before the arguments which were parsed
+        Some([name]) => {
+            let argument = 1 + arguments.flag_position("user").unwrap();
+            match resolve_user(msg, name, argument, telegram, context).await? {
+                Some(u) => Some(u),
+                None => return Ok(()),
+            }
+        }
+        Some(_) => reply!(USAGE.to_string()),
+        None => None,
+    };
+    let pack = match arguments.flag("pack") {
+        Some([pack]) => Some(pack.clone()),
+        Some(_) => reply!(USAGE.to_string()),
+        None => None,
+    };
+    let emoji = match arguments.flag("emoji") {
+        Some([emoji]) => Some(normalize_emoji(emoji)),
+        Some(_) => reply!(USAGE.to_string()),
+        None => None,
+    };
+    let top = match arguments.flag("top").map(|n| n.join("").parse::<usize>()) {
+        Some(Ok(n)) if (1..=MAX_STICKERS).contains(&n) => n,
+        Some(_) => reply!(format!("--top takes a number from 1 to {}", MAX_STICKERS)),
+        None => DEFAULT_STICKERS,
+    };
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let (caption, stickers) = {
+        let parsed_time = parse_time(&arguments.positional);
+        if !arguments.positional.is_empty() && parsed_time.is_none() {
+            reply!("Invalid time string".to_string());
         }
         let from_time: DateTime<Utc> = match parsed_time {
             Some(t) => Utc::now() - t,
@@ -170,7 +345,7 @@ pub async fn stickerlog<'a>(
         let (total_stickers, packs): (i64, i64) = conn
             .query_one(
                 include_sql!("getstickerstats.sql"),
-                params![msg.chat.id, from_time.timestamp()],
+                params![chatid, from_time.timestamp(), userid, pack, emoji, topic],
             )
             .await
             .map(|row| (row.get(0), row.get(1)))
@@ -179,7 +354,7 @@ pub async fn stickerlog<'a>(
         if total_stickers == 0 {
             telegram
                 .send_message_silent(
-                    msg.chat.id,
+                    msg,
                     format!(
                         "I have no recorded stickers after {}",
                         from_time
@@ -193,9 +368,25 @@ pub async fn stickerlog<'a>(
             return Ok(());
         }
 
+        let mut filters = String::new();
+        if let Some(u) = userid {
+            let mut redis = context.redis_pool.get().await;
+            filters += &format!(
+                " by {}",
+                get_user(chatid, u, telegram, context, &mut redis).await
+            );
+        }
+        if let Some(ref pack) = pack {
+            filters += &format!(" in pack {}", pack);
+        }
+        if let Some(ref emoji) = emoji {
+            filters += &format!(" with {}", emoji);
+        }
+
         let caption = format!(
-            "{} sent stickers from {} packs since {}",
+            "{} sent stickers{} from {} packs since {}",
             total_stickers,
+            filters,
             packs,
             if from_time.naive_utc().timestamp() == 0 {
                 "the dawn of time".to_string()
@@ -210,52 +401,68 @@ pub async fn stickerlog<'a>(
         );
 
         //Image rendering data
-        let (hashes, mut usages): (Vec<Vec<u8>>, Vec<i64>) = conn
+        let counts = conn
             .query(
                 include_sql!("getstickercounts.sql"),
-                params![msg.chat.id, from_time.timestamp()],
+                params![chatid, from_time.timestamp(), userid, pack, emoji, topic],
             )
             .await
             .map_err(|e| format!("getting sticker counts: {:?}", e))?
             .into_iter()
-            .map(|row| (row.get(0), row.get::<usize, i64>(1)))
-            .unzip();
-
-        let statement = conn
-            .prepare(include_sql!("getstickerfromhash.sql"))
-            .await
-            .unwrap();
+            .map(|row| StickerCount {
+                fileid: row.get(0),
+                thumbnailid: row.get(1),
+                animated: row.get(2),
+                phash: row.get(3),
+                uses: row.get(4),
+                packname: row.get(5),
+                hash: row.get(6),
+                emoji: row.get(7),
+            })
+            .collect();
 
         let mut redis = context.redis_pool.get().await;
-        let mut images = Vec::new();
-        for (index, hash) in hashes.iter().enumerate() {
-            let id = conn
-                .query_one(&statement, &[&hash])
-                .await
-                .map(|row| row.get::<usize, String>(0))
-                .map_err(|e| format!("getting sticker file id from hash: {}", e))?;
-
-            let image = telegram
-                .download_file(&mut redis, &id)
-                .await
-                .map_err(|e| format!("downloading file {}: {}", id, e))?;
-
-            if &image[0..4] != b"RIFF" {
-                info!("Sticker {} is not a webp, ignoring", id);
-                usages.remove(index);
+        let mut packs = HashMap::new();
+        let mut stickers = Vec::new();
+        for count in group_similar(counts).into_iter().take(top) {
+            //Animated and video stickers can't be drawn, but their thumbnails can
+            let mut animated = count.animated;
+            let id = if count.animated {
+                count.thumbnailid.as_deref()
             } else {
-                images.push(image);
+                Some(count.fileid.as_str())
+            };
+            let mut image = match id {
+                Some(id) => download_image(id, telegram, &mut redis).await,
+                None => None,
+            };
+
+            //Stickers logged before thumbnails were only have their own file, which can be
+            //animated without being marked as such. Their pack still has the thumbnail.
+            if count.thumbnailid.is_none() && image.is_none() {
+                if let Some(sticker) = pack_sticker(&count, &mut packs, telegram, &mut redis).await
+                {
+                    animated = sticker.is_animated || sticker.is_video;
+                    if let Some(id) = sticker.image_file_id() {
+                        image = download_image(id, telegram, &mut redis).await;
+                    }
+                }
             }
+            stickers.push(ChartSticker {
+                image,
+                animated,
+                uses: count.uses,
+            });
         }
 
-        (caption, images, usages)
+        (caption, stickers)
     };
 
     //Actual image rendering
-    let rendered_image = task::block_in_place(|| render_image(images, usages))?;
+    let rendered_image = task::block_in_place(|| render_image(stickers))?;
 
     telegram
-        .send_png_lossless(msg.chat.id, rendered_image, Some(caption), true)
+        .send_png_lossless(msg, rendered_image, Some(caption), true)
         .await
         .map(|_| ())
         .map_err(|e| format!("sending image: {}", e))
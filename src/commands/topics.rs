@@ -0,0 +1,57 @@
+use crate::{
+    include_sql, params,
+    telegram::{message::Message, Telegram},
+    util::{align_text_after, GENERAL_TOPIC},
+    Context,
+};
+use chrono::prelude::*;
+
+//Usage: /topics
+pub async fn topics(msg: &Message, telegram: &Telegram, context: &Context) -> Result<(), String> {
+    let conn = context.db_pool.get().await.unwrap();
+    let topics = conn
+        .query(include_sql!("topics/getactivity.sql"), params![msg.chat.id])
+        .await
+        .map_err(|e| format!("getting topic activity: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4)))
+        .collect::<Vec<(i64, Option<String>, i64, i64, i64)>>();
+
+    if topics.iter().all(|t| t.0 == GENERAL_TOPIC) {
+        return telegram
+            .send_message_silent(msg, "I haven't seen any topics in this chat".into())
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending no topics message: {}", e));
+    }
+
+    let mut table = String::new();
+    for (topic, name, messages, users, last_active) in topics {
+        //Topics created before the bot joined have no known name
+        let name = match name {
+            Some(n) => n,
+            None if topic == GENERAL_TOPIC => "General".to_string(),
+            None => format!("Topic {}", topic),
+        };
+        let date = Local
+            .timestamp_opt(last_active, 0)
+            .unwrap()
+            .format(&context.config.general.time_format);
+        table += &format!(
+            "{}: {} messages from {} users, last active {}\n",
+            name, messages, users, date
+        );
+    }
+
+    telegram
+        .send_message_silently_with_markdown(
+            msg,
+            format!(
+                "```\nActivity per topic:\n{}```",
+                align_text_after(':', table)
+            ),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending topic activity: {}", e))
+}
@@ -0,0 +1,377 @@
+use super::pickuser::resolve_user;
+use super::settings;
+use crate::{
+    include_sql, params,
+    render::{line_chart, Series},
+    telegram::{message::Message, Telegram},
+    tokenizer::tokenize,
+    util::{align_text_after, get_user, topic_scope, Arguments},
+    Context,
+};
+use chrono::prelude::*;
+use std::collections::HashMap;
+use tokio::task;
+
+//Words used less than this many times say more about chance than about the person
+const MYWORDS_MIN_USES: i64 = 3;
+const MYWORDS_SHOWN: usize = 15;
+const MYWORDS_USAGE: &str = "Usage: /mywords [<user>] [--topic [<name>]]";
+
+//Ranks a user's words by TF-IDF, treating everything each user has said as one document.
+//Words everyone uses get an IDF of zero, so what's left is what sets the user apart.
+pub async fn mywords(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    let arguments = Arguments::parse(&args[1..]);
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending mywords error message: {}", e))
+        };
+    }
+
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, MYWORDS_USAGE));
+    }
+    let userid = match arguments.positional.as_slice() {
+        [name] => match resolve_user(msg, name, 1, telegram, context).await? {
+            Some(u) => u,
+            None => return Ok(()),
+        },
+        [] => msg.from.id,
+        _ => reply!(MYWORDS_USAGE.to_string()),
+    };
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let stop_words = settings::stop_words(chatid, context).await?.words();
+    let conn = context.db_pool.get().await.unwrap();
+    //The user's words, with how many users have used each of them
+    let words: Vec<(String, i64, i64)> = conn
+        .query(
+            include_sql!("words/getuserwords.sql"),
+            params![chatid, userid, topic, stop_words],
+        )
+        .await
+        .map_err(|e| format!("getting user words: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect();
+    let users: i64 = conn
+        .query_one(
+            include_sql!("words/getusercount.sql"),
+            params![chatid, topic],
+        )
+        .await
+        .map(|row| row.get(0))
+        .map_err(|e| format!("getting user count: {:?}", e))?;
+
+    let mut redis = context.redis_pool.get().await;
+    let name = get_user(chatid, userid, telegram, context, &mut redis).await;
+    if words.is_empty() {
+        return telegram
+            .send_message_silent(msg, format!("I have no logged messages from {}", name))
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending no messages message: {}", e));
+    }
+
+    if users < 2 {
+        return telegram
+            .send_message_silent(
+                msg,
+                "I need messages from more than one person to compare with".into(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending too few users message: {}", e));
+    }
+
+    let total_words: i64 = words.iter().map(|(_, uses, _)| uses).sum();
+    let mut scored: Vec<(&str, i64, i64, f64)> = words
+        .iter()
+        .filter(|(_, uses, _)| *uses >= MYWORDS_MIN_USES)
+        .map(|(word, uses, frequency)| {
+            let tf = *uses as f64 / total_words as f64;
+            let idf = (users as f64 / *frequency as f64).ln();
+            (word.as_str(), *uses, *frequency, tf * idf)
+        })
+        .filter(|(_, _, _, score)| *score > 0.0)
+        .collect();
+
+    if scored.is_empty() {
+        return telegram
+            .send_message_silent(msg, format!("None of {}'s words stand out yet", name))
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("sending no distinctive words message: {}", e));
+    }
+
+    scored.sort_unstable_by(|a, b| b.3.partial_cmp(&a.3).unwrap().then_with(|| a.0.cmp(b.0)));
+
+    let mut table = String::new();
+    for (word, uses, frequency, _) in scored.into_iter().take(MYWORDS_SHOWN) {
+        table += &format!(
+            "{}: {} uses, said by {}/{} people\n",
+            word, uses, frequency, users
+        );
+    }
+
+    telegram
+        .send_message_silently_with_markdown(
+            msg,
+            format!(
+                "```\nWords that set {} apart from the rest of the chat:\n{}```",
+                name,
+                align_text_after(':', table)
+            ),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending distinctive words: {}", e))
+}
+
+const DAY: i64 = 24 * 3600;
+const WORDTREND_USAGE: &str = "Usage: /wordtrend <word> [--topic [<name>]]";
+
+//Picks a bucket size giving a reasonable number of points for the time span
+fn trend_bucket(span: i64) -> (i64, &'static str) {
+    if span <= 90 * DAY {
+        (DAY, "day")
+    } else if span <= 2 * 365 * DAY {
+        (7 * DAY, "week")
+    } else {
+        (30 * DAY, "month")
+    }
+}
+
+//Charts how often a word is used over time, relative to how much is being said
+pub async fn wordtrend(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending word trend message: {}", e))
+        };
+    }
+
+    let arguments = Arguments::parse(&args[1..]);
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, WORDTREND_USAGE));
+    }
+    let mut tokens = match arguments.positional.as_slice() {
+        [word] => tokenize(word),
+        _ => reply!(WORDTREND_USAGE.to_string()),
+    };
+    if tokens.len() != 1 {
+        reply!(format!(
+            "'{}' is not a single word",
+            arguments.positional[0]
+        ));
+    }
+    let word = tokens.remove(0);
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+
+    let conn = context.db_pool.get().await.unwrap();
+    let messages: Vec<(String, i64)> = conn
+        .query(include_sql!("getmessagetext.sql"), params![chatid, topic])
+        .await
+        .map_err(|e| format!("getting message text: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(2)))
+        .collect();
+
+    let (first, last) = match (messages.first(), messages.last()) {
+        (Some(f), Some(l)) => (f.1, l.1),
+        _ => reply!("There are no logged messages in this chat!".to_string()),
+    };
+    let (bucket_size, bucket_name) = trend_bucket(last - first);
+
+    //Messages and uses of the word in each bucket
+    let buckets = ((last - first) / bucket_size + 1) as usize;
+    let mut totals = vec![(0i64, 0i64); buckets];
+    for (message, instant) in &messages {
+        let bucket = &mut totals[((instant - first) / bucket_size) as usize];
+        bucket.0 += 1;
+        bucket.1 += tokenize(message).iter().filter(|w| **w == word).count() as i64;
+    }
+
+    let uses: i64 = totals.iter().map(|(_, uses)| uses).sum();
+    if uses == 0 {
+        reply!(format!("I have never seen the word '{}'", word));
+    }
+
+    let points = totals
+        .iter()
+        .enumerate()
+        .map(|(index, (messages, uses))| {
+            let per_thousand = if *messages == 0 {
+                0.0
+            } else {
+                *uses as f64 * 1000.0 / *messages as f64
+            };
+            (first + index as i64 * bucket_size, per_thousand)
+        })
+        .collect();
+
+    let title = format!("Uses of '{}' per 1000 messages each {}", word, bucket_name);
+    let series = [Series {
+        label: word.clone(),
+        points,
+    }];
+    let image = task::block_in_place(|| line_chart(&title, &series))?;
+
+    telegram
+        .send_png_lossless(
+            msg,
+            image,
+            Some(format!("I have seen '{}' {} time(s) in total", word, uses)),
+            true,
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending word trend chart: {}", e))
+}
+
+const TRENDING_DEFAULT_DAYS: i64 = 7;
+//Ten years, which is more history than any chat has
+const TRENDING_MAX_DAYS: i64 = 3650;
+//Words used fewer times than this recently are too rare to call trending
+const TRENDING_MIN_USES: i64 = 3;
+const TRENDING_SHOWN: usize = 15;
+const TRENDING_USAGE: &str = "Usage: /trending [<days>] [--topic [<name>]]";
+
+//Finds the words used far more in the last days than the chat's history would predict.
+//The expected number of uses comes from the word's add-one smoothed frequency before the
+//period, and words are ranked by how many standard deviations above that they are.
+pub async fn trending(
+    msg: &Message,
+    args: &[String],
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let chatid = msg.chat.id;
+    macro_rules! reply {
+        ($text:expr) => {
+            return telegram
+                .send_message_silent(msg, $text)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("sending trending message: {}", e))
+        };
+    }
+
+    let arguments = Arguments::parse(&args[1..]);
+    if let Some(flag) = arguments.unknown_flag(&["topic"]) {
+        reply!(format!("Unknown option --{}\n{}", flag, TRENDING_USAGE));
+    }
+    let days = match arguments.positional.as_slice() {
+        [] => TRENDING_DEFAULT_DAYS,
+        [d] => match d.parse::<i64>() {
+            Ok(d) if d > TRENDING_MAX_DAYS => reply!(format!(
+                "Trends can cover at most {} days",
+                TRENDING_MAX_DAYS
+            )),
+            Ok(d) if d > 0 => d,
+            _ => reply!(TRENDING_USAGE.to_string()),
+        },
+        _ => reply!(TRENDING_USAGE.to_string()),
+    };
+    let topic = match topic_scope(msg, &arguments, &context.db_pool).await {
+        Ok(t) => t,
+        Err(e) => reply!(e),
+    };
+    let cutoff = Utc::now().timestamp() - days * DAY;
+
+    let conn = context.db_pool.get().await.unwrap();
+    let messages: Vec<(String, i64)> = conn
+        .query(include_sql!("getmessagetext.sql"), params![chatid, topic])
+        .await
+        .map_err(|e| format!("getting message text: {:?}", e))?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(2)))
+        .collect();
+    let stop_words = settings::stop_words(chatid, context).await?;
+
+    let mut recent: HashMap<String, i64> = HashMap::new();
+    let mut baseline: HashMap<String, i64> = HashMap::new();
+    for (message, instant) in &messages {
+        let counts = if *instant >= cutoff {
+            &mut recent
+        } else {
+            &mut baseline
+        };
+        for word in tokenize(message) {
+            if !stop_words.contains(&word) {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let recent_total: i64 = recent.values().sum();
+    let baseline_total: i64 = baseline.values().sum();
+    if recent_total == 0 || baseline_total == 0 {
+        reply!(format!(
+            "I need messages from both the last {} days and before that to find trends",
+            days
+        ));
+    }
+
+    let vocabulary =
+        recent.keys().filter(|w| !baseline.contains_key(*w)).count() as f64 + baseline.len() as f64;
+    let mut scored: Vec<(&str, i64, f64, f64)> = recent
+        .iter()
+        .filter(|(_, uses)| **uses >= TRENDING_MIN_USES)
+        .map(|(word, uses)| {
+            let before = *baseline.get(word).unwrap_or(&0) as f64;
+            let expected =
+                recent_total as f64 * (before + 1.0) / (baseline_total as f64 + vocabulary);
+            let score = (*uses as f64 - expected) / expected.sqrt();
+            (word.as_str(), *uses, *uses as f64 / expected, score)
+        })
+        .filter(|(_, _, ratio, _)| *ratio > 1.0)
+        .collect();
+    scored.sort_unstable_by(|a, b| b.3.partial_cmp(&a.3).unwrap().then_with(|| a.0.cmp(b.0)));
+
+    if scored.is_empty() {
+        reply!(format!("Nothing stands out in the last {} days", days));
+    }
+
+    let mut table = String::new();
+    for (word, uses, ratio, _) in scored.into_iter().take(TRENDING_SHOWN) {
+        table += &format!("{}: {} uses, {:.1}x the usual\n", word, uses, ratio);
+    }
+
+    telegram
+        .send_message_silently_with_markdown(
+            msg,
+            format!(
+                "```\nTrending in the last {} days:\n{}```",
+                days,
+                align_text_after(':', table)
+            ),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending trending words: {}", e))
+}
@@ -1,15 +1,21 @@
 use crate::{
     commands::{self, handle_command},
-    include_sql, params,
+    image, include_sql, indexer, params,
     telegram::{
+        callbackquery::CallbackQuery,
         chat::ChatType,
         message::{Message, MessageData},
+        reaction::{MessageReaction, MessageReactionCount, ReactionType},
         update::Update,
+        user::ChatMemberUpdated,
         Telegram,
     },
-    util::{calculate_sticker_hash, get_user_id},
+    util::{
+        calculate_perceptual_hash, calculate_sticker_hash, chat_redis_keys, find_user, UserMatch,
+    },
     Context,
 };
+use darkredis::Command;
 use tokio_postgres::types::Type;
 
 pub async fn handle_update(update: Update, telegram: &Telegram, context: &Context) {
@@ -29,27 +35,188 @@ pub async fn handle_update(update: Update, telegram: &Telegram, context: &Contex
 
             info!("[{}] user {} edited message {}", msg.chat, msg.from, msg.id,)
         }
+        CallbackQuery(query) => handle_callback_query(&query, telegram, context).await,
+        Reaction(reaction) => log_reaction(&reaction, context).await,
+        ReactionCount(count) => log_reaction_count(&count, context).await,
+        ChatMember(update) | MyChatMember(update) => {
+            handle_member_update(&update, telegram, context).await
+        }
         _ => warn!("Update event {:?} not handled!", update),
     }
 }
 
+//Callback data is formatted as kind:payload, where kind decides who handles it
+async fn handle_callback_query(query: &CallbackQuery, telegram: &Telegram, context: &Context) {
+    let data = query.data.as_deref().unwrap_or("");
+    let (kind, payload) = data.split_once(':').unwrap_or((data, ""));
+    info!("<{}> pressed button {}", query.from, data);
+    let res = match kind {
+        "search" => commands::search::handle_callback(query, payload, telegram, context).await,
+        "disasterpoints" => {
+            commands::disaster::handle_callback(query, payload, telegram, context).await
+        }
+        "disastervote" => {
+            commands::disastervote::handle_callback(query, payload, telegram, context).await
+        }
+        "pickuser" => commands::pickuser::handle_callback(query, payload, telegram, context).await,
+        _ => {
+            warn!("No handler for callback query {:?}", data);
+            telegram.answer_callback_query(&query.id, None).await
+        }
+    };
+
+    if let Err(e) = res {
+        error!("Callback query '{}' failed at '{}'", kind, e);
+    }
+}
+
+async fn log_reaction(reaction: &MessageReaction, context: &Context) {
+    let mut conn = context.db_pool.get().await.unwrap();
+    let tx = conn.transaction().await.unwrap();
+    for removed in &reaction.removed {
+        tx.execute(
+            include_sql!("reactions/remove.sql"),
+            params![
+                reaction.chat.id,
+                reaction.message_id,
+                reaction.user.id,
+                removed.to_string()
+            ],
+        )
+        .await
+        .unwrap();
+    }
+    for added in &reaction.added {
+        tx.execute(
+            include_sql!("reactions/add.sql"),
+            params![
+                reaction.chat.id,
+                reaction.message_id,
+                reaction.user.id,
+                added.to_string(),
+                reaction.date
+            ],
+        )
+        .await
+        .unwrap();
+    }
+    tx.commit().await.unwrap();
+
+    let list = |reactions: &[ReactionType]| {
+        reactions
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    info!(
+        "[{}] {} reacted to {}: +[{}] -[{}]",
+        reaction.chat,
+        reaction.user,
+        reaction.message_id,
+        list(&reaction.added),
+        list(&reaction.removed)
+    );
+}
+
+async fn log_reaction_count(count: &MessageReactionCount, context: &Context) {
+    let mut conn = context.db_pool.get().await.unwrap();
+    let tx = conn.transaction().await.unwrap();
+    tx.execute(
+        include_sql!("reactions/clearcounts.sql"),
+        params![count.chat.id, count.message_id],
+    )
+    .await
+    .unwrap();
+    for (reaction, total) in &count.reactions {
+        tx.execute(
+            include_sql!("reactions/setcount.sql"),
+            params![count.chat.id, count.message_id, reaction.to_string(), total],
+        )
+        .await
+        .unwrap();
+    }
+    tx.commit().await.unwrap();
+
+    debug!(
+        "[{}] reaction counts of {} changed at {}",
+        count.chat, count.message_id, count.date
+    );
+}
+
 async fn log_message(telegram: &Telegram, msg: &Message, context: &Context) {
     match msg.data {
         MessageData::Text(ref text) => {
-            let conn = context.db_pool.get().await.unwrap();
-            let stmt = conn
+            let mut conn = context.db_pool.get().await.unwrap();
+            let tx = conn.transaction().await.unwrap();
+            let stmt = tx
                 .prepare_typed(
                     include_sql!("logmessage.sql"),
-                    &[Type::INT8, Type::INT8, Type::INT8, Type::TEXT, Type::INT8],
+                    &[
+                        Type::INT8,
+                        Type::INT8,
+                        Type::INT8,
+                        Type::TEXT,
+                        Type::INT8,
+                        Type::INT2,
+                        Type::INT8,
+                    ],
                 )
                 .await
                 .unwrap();
-            conn.execute(
+            tx.execute(
                 &stmt,
-                params![msg.id, msg.chat.id, msg.from.id, text, msg.date],
+                params![
+                    msg.id,
+                    msg.chat.id,
+                    msg.from.id,
+                    text,
+                    msg.date,
+                    indexer::INDEX_VERSION,
+                    msg.thread_id
+                ],
             )
             .await
             .unwrap();
+
+            let indexed = indexer::IndexedMessage {
+                chatid: msg.chat.id,
+                userid: msg.from.id,
+                msgid: msg.id,
+                instant: msg.date,
+                threadid: msg.thread_id,
+                text,
+                entity_links: msg
+                    .entities
+                    .iter()
+                    .filter_map(|e| match e.kind.as_str() {
+                        "text_link" => e.url.as_deref(),
+                        "url" => e.text(text),
+                        _ => None,
+                    })
+                    .collect(),
+            };
+            indexer::index_message(&tx, &indexed, 0).await.unwrap();
+
+            let (ids, fallbacks): (Vec<&str>, Vec<&str>) = msg
+                .entities
+                .iter()
+                .filter(|e| e.kind == "custom_emoji")
+                .filter_map(|e| Some((e.custom_emoji_id.as_deref()?, e.text(text)?)))
+                .unzip();
+            if !ids.is_empty() {
+                tx.execute(
+                    include_sql!("emoji/logcustomemoji.sql"),
+                    params![msg.chat.id, msg.from.id, msg.id, msg.date, ids, fallbacks],
+                )
+                .await
+                .unwrap();
+            }
+            tx.commit().await.unwrap();
+
+            if let Err(e) = commands::links::check_repost(msg, &indexed, telegram, context).await {
+                error!("Checking for reposts failed at '{}'", e);
+            }
         }
         MessageData::Sticker(ref sticker) => {
             let mut redis = context.redis_pool.get().await;
@@ -61,6 +228,10 @@ async fn log_message(telegram: &Telegram, msg: &Message, context: &Context) {
             if hash.is_err() {
                 return;
             } //this error gets logged elsewhere anyway
+            let phash = match sticker.image_file_id() {
+                Some(id) => calculate_perceptual_hash(telegram, &mut redis, id).await,
+                None => None,
+            };
 
             let conn = context.db_pool.get().await.unwrap();
             let stmt = conn
@@ -75,6 +246,10 @@ async fn log_message(telegram: &Telegram, msg: &Message, context: &Context) {
                         Type::TEXT,
                         Type::INT8,
                         Type::BYTEA,
+                        Type::INT8,
+                        Type::BOOL,
+                        Type::TEXT,
+                        Type::INT8,
                     ],
                 )
                 .await
@@ -90,15 +265,108 @@ async fn log_message(telegram: &Telegram, msg: &Message, context: &Context) {
                     sticker.set_name,
                     msg.date,
                     hash.unwrap(),
+                    phash,
+                    sticker.is_animated || sticker.is_video,
+                    sticker.thumbnail.as_ref().map(|t| &t.file_id),
+                    msg.thread_id,
                 ],
             )
             .await
             .unwrap();
         }
+        MessageData::Photo(ref sizes) => {
+            //The smallest size which isn't tiny is plenty to hash
+            let photo = match sizes.iter().find(|p| p.width.min(p.height) >= 128) {
+                Some(p) => p,
+                None => sizes.last().unwrap(),
+            };
+            let mut redis = context.redis_pool.get().await;
+            let phash = calculate_perceptual_hash(telegram, &mut redis, &photo.file_id).await;
+
+            let conn = context.db_pool.get().await.unwrap();
+            conn.execute(
+                include_sql!("photos/logphoto.sql"),
+                params![
+                    msg.chat.id,
+                    msg.from.id,
+                    msg.id,
+                    photo.file_id,
+                    phash,
+                    msg.date
+                ],
+            )
+            .await
+            .unwrap();
+
+            if let Some(phash) = phash {
+                if let Err(e) = reply_if_old(msg, phash, telegram, context).await {
+                    error!("Checking for old photos failed at '{}'", e);
+                }
+            }
+        }
+        MessageData::MembersJoined(ref users) => {
+            for user in users {
+                log_membership(msg.chat.id, user.id, true, msg.date, context).await;
+            }
+        }
+        MessageData::MemberLeft(ref user) => {
+            log_membership(msg.chat.id, user.id, false, msg.date, context).await;
+        }
+        MessageData::TopicCreated(ref name) | MessageData::TopicEdited(ref name) => {
+            //The message creating a topic is the first message in it
+            let threadid = msg.thread_id.unwrap_or(msg.id);
+            let conn = context.db_pool.get().await.unwrap();
+            conn.execute(
+                include_sql!("topics/setname.sql"),
+                params![msg.chat.id, threadid, name],
+            )
+            .await
+            .unwrap();
+        }
         _ => (), //other message types are not logged yet
     }
 }
 
+//Replies "old!" to photos which look like one posted within the chat's window
+async fn reply_if_old(
+    msg: &Message,
+    phash: i64,
+    telegram: &Telegram,
+    context: &Context,
+) -> Result<(), String> {
+    let days: i64 = commands::settings::get(msg.chat.id, "oldwindow", context)
+        .await?
+        .parse()
+        .unwrap_or(0);
+    if days == 0 {
+        return Ok(());
+    }
+
+    let conn = context.db_pool.get().await.unwrap();
+    let earlier = conn
+        .query_opt(
+            include_sql!("photos/getsimilar.sql"),
+            params![
+                msg.chat.id,
+                msg.id,
+                msg.date - days * 24 * 3600,
+                phash,
+                image::SIMILAR_DISTANCE as i32
+            ],
+        )
+        .await
+        .map_err(|e| format!("getting similar photos: {:?}", e))?;
+    if earlier.is_none() {
+        return Ok(());
+    }
+
+    telegram
+        .reply_to(msg.id, msg, "old!".into())
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("sending old message: {}", e))
+}
+
 async fn handle_text_reply(
     text: &str,
     telegram: &Telegram,
@@ -112,83 +380,236 @@ async fn handle_text_reply(
     match redis.get(&key).await {
         Ok(Some(command)) => {
             let command: commands::ReplyCommand = rmp_serde::from_slice(&command).unwrap();
-            let userid = get_user_id(msg.chat.id, text, &context.db_pool).await;
-            if let Some(userid) = userid {
-                match command.action {
-                    commands::ReplyAction::Quote => {
-                        let res = crate::commands::quote(
-                            userid,
-                            msg.chat.id,
-                            command.command_message_id,
-                            telegram,
-                            context,
+            let userid = match find_user(msg.chat.id, text, &context.db_pool).await {
+                UserMatch::Found(u) => u,
+                UserMatch::NotFound => {
+                    if let Err(e) = telegram
+                        .reply_and_close_keyboard(
+                            msg.id,
+                            msg,
+                            format!("I haven't seen {} yet", text),
                         )
-                        .await;
+                        .await
+                    {
+                        error!("failed to send unknown user message: {}", e);
+                    }
+                    return;
+                }
+                //Ask which one is meant, as if the name had been given with the command
+                UserMatch::Ambiguous(_) => {
+                    let mention = match msg.chat.kind {
+                        ChatType::Private => "",
+                        _ => telegram.bot_mention(),
+                    };
+                    let command_message = Message {
+                        id: command.command_message_id,
+                        data: MessageData::Text(format!(
+                            "{}{} {}",
+                            command.action.command(),
+                            mention,
+                            text
+                        )),
+                        ..msg.clone()
+                    };
+                    if let Err(e) = commands::pickuser::resolve_user(
+                        &command_message,
+                        text,
+                        1,
+                        telegram,
+                        context,
+                    )
+                    .await
+                    {
+                        error!("failed to ask which user a reply meant: {}", e);
+                    }
+                    futures::future::join(
+                        telegram.delete_message(msg.chat.id, msg.id),
+                        telegram.delete_message(msg.chat.id, other_message.id),
+                    )
+                    .await;
+                    return;
+                }
+            };
+            match command.action {
+                commands::ReplyAction::Quote => {
+                    let res = crate::commands::quote(
+                        userid,
+                        msg.into(),
+                        command.command_message_id,
+                        telegram,
+                        context,
+                    )
+                    .await;
 
-                        match res {
-                            Err(e) => error!("failed to quote from reply message: {}", e),
-                            Ok(_) => commands::log_command("quote", context, msg).await,
-                        }
+                    match res {
+                        Err(e) => error!("failed to quote from reply message: {}", e),
+                        Ok(_) => commands::log_command("quote", context, msg).await,
                     }
-                    commands::ReplyAction::Simulate => {
-                        let res = crate::commands::simulate(
-                            userid,
-                            msg.chat.id,
-                            context.config.markov.chain_order,
-                            command.command_message_id,
-                            telegram,
-                            context,
-                            None,
-                        )
-                        .await;
+                }
+                commands::ReplyAction::Simulate => {
+                    let res = crate::commands::simulate(
+                        userid,
+                        msg.into(),
+                        context.config.markov.chain_order,
+                        command.command_message_id,
+                        telegram,
+                        context,
+                        None,
+                    )
+                    .await;
 
-                        match res {
-                            Ok(()) => commands::log_command("simulate", context, msg).await,
-                            Err(e) => error!("failed to simulate from reply message: {}", e),
-                        }
+                    match res {
+                        Ok(()) => commands::log_command("simulate", context, msg).await,
+                        Err(e) => error!("failed to simulate from reply message: {}", e),
                     }
-                    commands::ReplyAction::AddDisasterPoint => {
-                        let res = crate::commands::disaster::add_point(
-                            userid,
-                            msg.from.id,
-                            msg.chat.id,
-                            command.command_message_id,
-                            msg.date,
-                            telegram,
-                            context,
-                        )
-                        .await;
+                }
+                commands::ReplyAction::AddDisasterPoint => {
+                    let res = crate::commands::disaster::add_disaster_point(
+                        userid,
+                        msg.from.id,
+                        msg.into(),
+                        command.command_message_id,
+                        None,
+                        telegram,
+                        context,
+                    )
+                    .await;
 
-                        match res {
-                            Ok(()) => commands::log_command("disaster", context, msg).await,
-                            Err(e) => {
-                                error!("failed to add a disaster point from reply message: {}", e)
-                            }
+                    match res {
+                        Ok(()) => commands::log_command("disaster", context, msg).await,
+                        Err(e) => {
+                            error!("failed to add a disaster point from reply message: {}", e)
                         }
                     }
                 }
-                futures::future::join(
-                    telegram.delete_message(msg.chat.id, msg.id),
-                    telegram.delete_message(msg.chat.id, other_message.id),
-                )
-                .await;
             }
+            futures::future::join(
+                telegram.delete_message(msg.chat.id, msg.id),
+                telegram.delete_message(msg.chat.id, other_message.id),
+            )
+            .await;
         }
         Ok(None) => (),
         Err(e) => error!("redis error getting reply command: {:?}", e),
     }
 }
 
+//Redis keys which hold state rather than caches or things about messages of a chat. These are
+//moved along when a chat migrates and kept when the bot leaves.
+const PERSISTENT_REDIS_KEYS: &[&str] = &["tg.disastercooldown."];
+
+//Re-keys everything stored about a group to the id of the supergroup it became. Both the old and
+//the new chat get a message about it, so this runs twice and the second time finds nothing.
+async fn migrate_chat(from: i64, to: i64, context: &Context) -> Result<(), String> {
+    let mut conn = context.db_pool.get().await.unwrap();
+    let tx = conn
+        .transaction()
+        .await
+        .map_err(|e| format!("starting transaction: {:?}", e))?;
+    tx.execute(include_sql!("migratechat.sql"), params![from, to])
+        .await
+        .map_err(|e| format!("moving chat data: {:?}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("committing chat migration: {:?}", e))?;
+
+    let mut redis = context.redis_pool.get().await;
+    let old_id = from.to_string();
+    for key in chat_redis_keys(from, &mut redis).await {
+        let new_key = PERSISTENT_REDIS_KEYS.iter().find_map(|prefix| {
+            key.strip_prefix(prefix)?
+                .strip_prefix(&old_id)
+                .map(|rest| format!("{}{}{}", prefix, to, rest))
+        });
+        match new_key {
+            Some(new_key) => {
+                redis
+                    .run_command(Command::new("RENAME").arg(&key).arg(&new_key))
+                    .await
+                    .map_err(|e| format!("renaming {}: {:?}", key, e))?;
+            }
+            None => {
+                redis
+                    .del(&key)
+                    .await
+                    .map_err(|e| format!("deleting {}: {:?}", key, e))?;
+            }
+        }
+    }
+
+    info!("Moved all data of chat {} to {}", from, to);
+    Ok(())
+}
+
+//Drops the cached data of a chat the bot was removed from
+async fn forget_chat(chatid: i64, context: &Context) -> Result<(), String> {
+    let mut redis = context.redis_pool.get().await;
+    for key in chat_redis_keys(chatid, &mut redis).await {
+        if !PERSISTENT_REDIS_KEYS.iter().any(|k| key.starts_with(k)) {
+            redis
+                .del(&key)
+                .await
+                .map_err(|e| format!("deleting {}: {:?}", key, e))?;
+        }
+    }
+    Ok(())
+}
+
+async fn log_membership(chatid: i64, userid: i64, joined: bool, instant: i64, context: &Context) {
+    let conn = context.db_pool.get().await.unwrap();
+    conn.execute(
+        include_sql!("members/addevent.sql"),
+        params![chatid, userid, joined, instant],
+    )
+    .await
+    .unwrap();
+}
+
+async fn handle_member_update(update: &ChatMemberUpdated, telegram: &Telegram, context: &Context) {
+    let (was_present, is_present) = (update.old.is_present(), update.new.is_present());
+    //Promotions, restrictions and such
+    if was_present == is_present {
+        return;
+    }
+
+    let user = &update.new.user;
+    log_membership(update.chat.id, user.id, is_present, update.date, context).await;
+    let by = if update.from.id == user.id {
+        String::new()
+    } else {
+        format!(" (by {})", update.from)
+    };
+    info!(
+        "[{}] {} {}{}",
+        update.chat,
+        user,
+        if is_present { "joined" } else { "left" },
+        by
+    );
+
+    if user.id == telegram.bot_user().id && !is_present {
+        if let Err(e) = forget_chat(update.chat.id, context).await {
+            error!("Forgetting chat {} failed at '{}'", update.chat.id, e);
+        }
+    }
+}
+
 async fn handle_message(msg: &Message, telegram: &Telegram, context: &Context) {
     let mut should_log = !matches!(msg.chat.kind, ChatType::Private);
 
     match msg.data {
-        MessageData::Text(ref text) => {
-            //Is command
-            if text.starts_with('/') {
-                handle_command(msg, text, telegram, context).await;
-                should_log = false;
+        MessageData::ChatMigrated { from, to } => {
+            if let Err(e) = migrate_chat(from, to, context).await {
+                error!("Migrating chat {} to {} failed at '{}'", from, to, e);
             }
+            //Nothing more to do, and the user snapshot would end up under the old chat id
+            info!("[{}] <{}>: {}", msg.chat, msg.from, msg.data);
+            return;
+        }
+        //Is command
+        MessageData::Text(ref text) if text.starts_with('/') => {
+            handle_command(msg, text, telegram, context).await;
+            should_log = false;
         }
         MessageData::Reply(ref data, ref other_message) => {
             //Replying to the bot
@@ -200,6 +621,21 @@ async fn handle_message(msg: &Message, telegram: &Telegram, context: &Context) {
                 } else {
                     return;
                 };
+            //Replying +1 or -1 to a member
+            } else if let MessageData::Text(ref text) = **data {
+                match commands::disaster::handle_point_reply(
+                    text,
+                    msg,
+                    other_message,
+                    telegram,
+                    context,
+                )
+                .await
+                {
+                    Ok(true) => commands::log_command("give", context, msg).await,
+                    Ok(false) => (),
+                    Err(e) => error!("failed to give a point from reply message: {}", e),
+                }
             }
         }
         _ => (),
@@ -229,6 +665,19 @@ async fn handle_message(msg: &Message, telegram: &Telegram, context: &Context) {
     )
     .await
     .unwrap();
+    conn.execute(
+        include_sql!("names/add.sql"),
+        params![
+            msg.chat.id,
+            msg.from.id,
+            msg.from.first_name,
+            msg.from.last_name,
+            msg.from.username,
+            msg.date
+        ],
+    )
+    .await
+    .unwrap();
 
     info!("[{}] <{}>: {}", msg.chat, msg.from, msg.data);
 }
@@ -0,0 +1,121 @@
+use libc::c_int;
+
+//A decoded image as RGBA pixels
+pub struct Image {
+    pub data: Vec<u8>,
+    pub width: i32,
+    pub height: i32,
+}
+
+//Hashes at most this many bits apart are considered the same picture
+pub const SIMILAR_DISTANCE: u32 = 4;
+
+fn decode_webp(input: &[u8]) -> Result<Image, String> {
+    let mut width: c_int = 0;
+    let mut height: c_int = 0;
+    unsafe {
+        let data = libwebp_sys::WebPDecodeRGBA(
+            input.as_ptr(),
+            input.len(),
+            &mut width as *mut c_int,
+            &mut height as *mut c_int,
+        );
+
+        if data.is_null() {
+            return Err("decoding image as webp".to_string());
+        }
+        let size = width as usize * height as usize * 4; // RGBA
+        let pixels = std::slice::from_raw_parts(data, size).to_vec();
+        libwebp_sys::WebPFree(data as *mut std::ffi::c_void);
+        Ok(Image {
+            data: pixels,
+            width,
+            height,
+        })
+    }
+}
+
+fn decode_jpeg(input: &[u8]) -> Result<Image, String> {
+    let mut decoder = jpeg_decoder::Decoder::new(input);
+    let pixels = decoder
+        .decode()
+        .map_err(|e| format!("decoding image as JPEG: {}", e))?;
+    let info = decoder.info().unwrap();
+    let data = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => pixels.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        jpeg_decoder::PixelFormat::RGB24 => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        other => return Err(format!("unsupported JPEG pixel format {:?}", other)),
+    };
+    Ok(Image {
+        data,
+        width: i32::from(info.width),
+        height: i32::from(info.height),
+    })
+}
+
+//Decodes a webp (stickers) or JPEG (photos and thumbnails) file
+pub fn decode(input: &[u8]) -> Result<Image, String> {
+    if input.get(0..4) == Some(b"RIFF") {
+        decode_webp(input)
+    } else {
+        decode_jpeg(input)
+    }
+}
+
+//Computes a difference hash: the image is shrunk to 9x8 greyscale and every bit says whether
+//a pixel is brighter than its right neighbour. Unlike hashing the file, this survives
+//re-encoding, resizing and small edits.
+pub fn dhash(pixels: &[u8], width: usize, height: usize) -> i64 {
+    const COLUMNS: usize = 9;
+    const ROWS: usize = 8;
+    if width == 0 || height == 0 {
+        return 0;
+    }
+
+    let luma = |x: usize, y: usize| -> f64 {
+        let p = &pixels[(y * width + x) * 4..];
+        0.299 * f64::from(p[0]) + 0.587 * f64::from(p[1]) + 0.114 * f64::from(p[2])
+    };
+
+    //Average the brightness of every block of pixels making up a cell
+    let mut cells = [[0.0; COLUMNS]; ROWS];
+    for (row, cells) in cells.iter_mut().enumerate() {
+        let y_start = row * height / ROWS;
+        let y_end = ((row + 1) * height / ROWS).max(y_start + 1).min(height);
+        for (column, cell) in cells.iter_mut().enumerate() {
+            let x_start = column * width / COLUMNS;
+            let x_end = ((column + 1) * width / COLUMNS).max(x_start + 1).min(width);
+            let mut sum = 0.0;
+            for y in y_start..y_end {
+                for x in x_start..x_end {
+                    sum += luma(x, y);
+                }
+            }
+            *cell = sum / ((y_end - y_start) * (x_end - x_start)) as f64;
+        }
+    }
+
+    let mut hash: u64 = 0;
+    for row in &cells {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] > pair[1]);
+        }
+    }
+    hash as i64
+}
+
+pub fn perceptual_hash(file: &[u8]) -> Result<i64, String> {
+    let image = decode(file)?;
+    Ok(dhash(
+        &image.data,
+        image.width as usize,
+        image.height as usize,
+    ))
+}
+
+pub fn hamming_distance(a: i64, b: i64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,156 @@
+use crate::{include_sql, links, params, tokenizer::tokenize, util::GENERAL_TOPIC};
+use deadpool_postgres::{Pool, Transaction};
+use std::collections::HashMap;
+
+//Data derived from logged messages, like n-gram counts, is built as messages are logged.
+//Every message remembers which version of the indexer has processed it. Bump this whenever
+//something new is derived from messages, and old messages get backfilled at startup.
+pub const INDEX_VERSION: i16 = 3;
+
+//Longest n-gram that gets counted
+pub const MAX_NGRAM: usize = 3;
+
+const BACKFILL_BATCH_SIZE: i64 = 1000;
+
+pub struct IndexedMessage<'a> {
+    pub chatid: i64,
+    pub userid: i64,
+    pub msgid: i64,
+    pub instant: i64,
+    pub threadid: Option<i64>,
+    pub text: &'a str,
+    //Links Telegram found in the text, which don't need a scheme, and links behind text. Only
+    //known when the message is first logged, as the entities of a message aren't stored.
+    pub entity_links: Vec<&'a str>,
+}
+
+//Builds everything derived from a message that the indexer at from_version hasn't already built
+pub async fn index_message(
+    tx: &Transaction<'_>,
+    msg: &IndexedMessage<'_>,
+    from_version: i16,
+) -> Result<(), String> {
+    if from_version < 2 {
+        add_links(tx, msg).await?;
+    }
+    //N-grams were first counted in version 1, but are rebuilt with words and topics in version 3
+    if from_version < 3 {
+        add_ngrams(tx, msg).await?;
+    }
+    Ok(())
+}
+
+pub fn ngrams(tokens: &[String], n: usize) -> impl Iterator<Item = String> + '_ {
+    tokens.windows(n).map(|w| w.join(" "))
+}
+
+async fn add_ngrams(tx: &Transaction<'_>, msg: &IndexedMessage<'_>) -> Result<(), String> {
+    let tokens = tokenize(msg.text);
+    let mut counts: HashMap<(i16, String), i64> = HashMap::new();
+    //Single words are counted too, for word statistics
+    for n in 1..=MAX_NGRAM {
+        for ngram in ngrams(&tokens, n) {
+            *counts.entry((n as i16, ngram)).or_insert(0) += 1;
+        }
+    }
+    if counts.is_empty() {
+        return Ok(());
+    }
+
+    let mut sizes = Vec::with_capacity(counts.len());
+    let mut texts = Vec::with_capacity(counts.len());
+    let mut uses = Vec::with_capacity(counts.len());
+    for ((n, ngram), count) in counts {
+        sizes.push(n);
+        texts.push(ngram);
+        uses.push(count);
+    }
+
+    tx.execute(
+        include_sql!("indexer/addngrams.sql"),
+        params![
+            msg.chatid,
+            msg.userid,
+            msg.threadid.unwrap_or(GENERAL_TOPIC),
+            sizes,
+            texts,
+            uses
+        ],
+    )
+    .await
+    .map(|_| ())
+    .map_err(|e| format!("adding n-grams: {:?}", e))
+}
+
+async fn add_links(tx: &Transaction<'_>, msg: &IndexedMessage<'_>) -> Result<(), String> {
+    let found = links::extract(msg.text, msg.entity_links.iter().copied());
+    if found.is_empty() {
+        return Ok(());
+    }
+
+    let (urls, domains): (Vec<String>, Vec<String>) =
+        found.into_iter().map(|l| (l.url, l.domain)).unzip();
+    tx.execute(
+        include_sql!("indexer/addlinks.sql"),
+        params![
+            msg.chatid,
+            msg.userid,
+            msg.msgid,
+            msg.instant,
+            urls,
+            domains
+        ],
+    )
+    .await
+    .map(|_| ())
+    .map_err(|e| format!("adding links: {:?}", e))
+}
+
+//Indexes the messages logged before the current version of the indexer existed
+pub async fn backfill(pool: Pool) {
+    let mut indexed = 0;
+    loop {
+        let mut conn = pool.get().await.unwrap();
+        let tx = conn.transaction().await.unwrap();
+        let messages = tx
+            .query(
+                include_sql!("indexer/getunindexed.sql"),
+                params![INDEX_VERSION, BACKFILL_BATCH_SIZE],
+            )
+            .await
+            .unwrap();
+        if messages.is_empty() {
+            break;
+        }
+
+        for row in &messages {
+            let msg = IndexedMessage {
+                chatid: row.get(0),
+                userid: row.get(1),
+                msgid: row.get(2),
+                instant: row.get(5),
+                threadid: row.get(6),
+                text: row.get(3),
+                entity_links: Vec::new(),
+            };
+            if let Err(e) = index_message(&tx, &msg, row.get(4)).await {
+                error!(
+                    "Failed to index message {} in {}: {}",
+                    msg.msgid, msg.chatid, e
+                );
+                return;
+            }
+            tx.execute(
+                include_sql!("indexer/setindexversion.sql"),
+                params![msg.chatid, msg.msgid, INDEX_VERSION],
+            )
+            .await
+            .unwrap();
+        }
+        tx.commit().await.unwrap();
+
+        indexed += messages.len();
+        info!("Indexed {} previously logged messages", indexed);
+    }
+    debug!("All logged messages are indexed");
+}
@@ -0,0 +1,82 @@
+use crate::tokenizer::{is_link, tokenize};
+use reqwest::Url;
+
+//Query parameters which only exist to track where a link was shared from
+const TRACKING_PARAMETERS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "mc_cid", "mc_eid",
+    "igshid", "igsh", "si", "ref_src", "ref_url", "_ga", "_gl", "s_cid", "trk",
+];
+
+pub struct Link {
+    pub url: String,
+    pub domain: String,
+}
+
+fn is_tracking_parameter(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMETERS.contains(&name.as_str())
+}
+
+//Normalizes a link so the same page shared in different ways compares equal.
+//The scheme, www prefix, fragment, trailing slash and tracking parameters are ignored,
+//and the remaining query parameters are sorted.
+pub fn normalize(link: &str) -> Option<Link> {
+    //Telegram also finds links like example.com/page which don't have a scheme
+    let mut url = if link.contains("://") {
+        Url::parse(link)
+    } else {
+        Url::parse(&format!("http://{}", link))
+    }
+    .ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+    let host = url.host_str()?.to_lowercase();
+    let domain = host.strip_prefix("www.").unwrap_or(&host).to_string();
+
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_parameter(name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    query.sort();
+
+    url.set_query(None);
+    if !query.is_empty() {
+        url.query_pairs_mut().extend_pairs(query);
+    }
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(&path);
+
+    let mut normalized = domain.clone();
+    if let Some(port) = url.port() {
+        normalized += &format!(":{}", port);
+    }
+    normalized += url.path();
+    if let Some(query) = url.query() {
+        normalized += "?";
+        normalized += query;
+    }
+
+    Some(Link {
+        url: normalized,
+        domain,
+    })
+}
+
+//Finds every link in a message, along with the links Telegram found in it. Links with a scheme
+//are found both ways, so each link is only kept once.
+pub fn extract<'a>(text: &str, entities: impl Iterator<Item = &'a str>) -> Vec<Link> {
+    let tokens = tokenize(text);
+    let found = tokens
+        .iter()
+        .filter(|t| is_link(t))
+        .filter_map(|t| normalize(t));
+    let mut links: Vec<Link> = Vec::new();
+    for link in found.chain(entities.filter_map(normalize)) {
+        if !links.iter().any(|l| l.url == link.url) {
+            links.push(link);
+        }
+    }
+    links
+}
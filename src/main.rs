@@ -9,7 +9,13 @@ use std::{fs::File, io::Read, path::Path, process::exit};
 
 mod commands;
 mod handlers;
+mod image;
+mod indexer;
+mod links;
+mod migrations;
+mod render;
 mod telegram;
+mod tokenizer;
 mod util;
 
 #[derive(Default, Deserialize)]
@@ -33,6 +39,13 @@ struct GeneralConfig {
 #[serde(deny_unknown_fields)]
 struct DisasterConfig {
     cooldown: u64,
+    //Minutes within which the giver can take a point back, missing from older configs
+    #[serde(default = "default_undo_grace")]
+    undo_grace: u64,
+}
+
+fn default_undo_grace() -> u64 {
+    10
 }
 
 #[derive(Default, Deserialize)]
@@ -81,6 +94,8 @@ async fn main() -> std::io::Result<()> {
 
 async fn real_main() -> std::io::Result<()> {
     env_logger::init();
+    //Only bring the database up to date, for running migrations separately from deployments
+    let migrate_only = std::env::args().skip(1).any(|a| a == "--migrate-only");
 
     let config_path = Path::new("tg.toml");
     info!(
@@ -102,22 +117,6 @@ async fn real_main() -> std::io::Result<()> {
     //maximum number of connections to redis and the database
     let max_connections = num_cpus::get() * 4;
     info!("Using {} pooled connections", max_connections);
-    info!("Opening Redis connections...");
-    let address = &config.redis.address;
-    let password = &config.redis.password;
-    let redis_pool = match darkredis::ConnectionPool::create(
-        address.into(),
-        password.as_ref().map(|p| &**p), //not pretty but it works
-        max_connections,
-    )
-    .await
-    {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Failed to connect to redis: {}", e);
-            exit(1);
-        }
-    };
     info!("Opening database connections...");
     let mut postgres_config = tokio_postgres::Config::new();
     postgres_config.host(&config.postgres.host);
@@ -136,12 +135,33 @@ async fn real_main() -> std::io::Result<()> {
         .build()
         .unwrap();
 
-    {
-        info!("Creating tables if necesarry...");
-        let db = db_pool.get().await.unwrap();
-        db.batch_execute(include_sql!("create.sql")).await.unwrap();
-        info!("Tables created!");
+    info!("Migrating the database...");
+    if let Err(e) = migrations::migrate(&db_pool).await {
+        error!("Migrating the database failed at '{}'", e);
+        exit(3);
     }
+    info!("Database up to date!");
+    if migrate_only {
+        return Ok(());
+    }
+
+    info!("Opening Redis connections...");
+    let address = &config.redis.address;
+    let password = &config.redis.password;
+    let redis_pool = match darkredis::ConnectionPool::create(
+        address.into(),
+        password.as_ref().map(|p| &**p), //not pretty but it works
+        max_connections,
+    )
+    .await
+    {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to connect to redis: {}", e);
+            exit(1);
+        }
+    };
+    tokio::spawn(indexer::backfill(db_pool.clone()));
 
     info!("Connecting to Telegram...");
     let telegram = Telegram::connect(std::env::var("TELEGRAM_BOT_TOKEN").unwrap()).await;
@@ -153,12 +173,19 @@ async fn real_main() -> std::io::Result<()> {
                 db_pool,
             };
 
+            //Ends seasons and votes of disaster points alongside handling updates, without restarting
+            //whenever the updates have to be listened to again
+            let ticker = futures::future::join(
+                commands::seasons::ticker(&telegram, &context),
+                commands::disastervote::ticker(&telegram, &context),
+            );
+            futures::pin_mut!(ticker);
             loop {
                 info!("Listening to updates...");
-                telegram
+                let updates = telegram
                     .updates()
-                    .for_each_concurrent(None, |f| handlers::handle_update(f, &telegram, &context))
-                    .await;
+                    .for_each_concurrent(None, |f| handlers::handle_update(f, &telegram, &context));
+                futures::future::select(Box::pin(updates), ticker.as_mut()).await;
             }
         }
         Err(e) => {
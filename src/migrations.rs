@@ -0,0 +1,106 @@
+use crate::{include_sql, params};
+use chrono::prelude::*;
+use deadpool_postgres::Pool;
+use md5::{Digest, Md5};
+
+//Every change to the schema ships as a new migration at the end of this list. Migrations which
+//have been released are never edited, their checksums are checked at startup.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_sql!("migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "getcommandid_function",
+        sql: include_sql!("migrations/0002_getcommandid_function.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "setting_changed",
+        sql: include_sql!("migrations/0003_setting_changed.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "ngram_topics",
+        sql: include_sql!("migrations/0004_ngram_topics.sql"),
+    },
+];
+
+//Makes sure only one instance migrates at a time, the value is arbitrary
+const MIGRATION_LOCK: i64 = 0x7467_6d69_6772;
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+fn checksum(sql: &str) -> String {
+    Md5::digest(sql.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+//Applies the migrations the database doesn't have yet, each in its own transaction. Fails if a
+//migration which was applied earlier has been changed since.
+pub async fn migrate(pool: &Pool) -> Result<(), String> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|e| format!("getting database connection: {:?}", e))?;
+    conn.batch_execute(include_sql!("schema/create.sql"))
+        .await
+        .map_err(|e| format!("creating migrations table: {:?}", e))?;
+
+    for migration in MIGRATIONS {
+        let tx = conn
+            .transaction()
+            .await
+            .map_err(|e| format!("starting transaction: {:?}", e))?;
+        tx.execute(include_sql!("schema/lock.sql"), params![MIGRATION_LOCK])
+            .await
+            .map_err(|e| format!("locking migrations: {:?}", e))?;
+
+        let expected = checksum(migration.sql);
+        let applied = tx
+            .query_opt(include_sql!("schema/get.sql"), params![migration.version])
+            .await
+            .map_err(|e| format!("getting migration {}: {:?}", migration.version, e))?;
+        if let Some(row) = applied {
+            let checksum: String = row.get(0);
+            if checksum != expected {
+                return Err(format!(
+                    "migration {} ({}) was changed after it was applied",
+                    migration.version, migration.name
+                ));
+            }
+            continue;
+        }
+
+        info!(
+            "Applying migration {} ({})...",
+            migration.version, migration.name
+        );
+        tx.batch_execute(migration.sql)
+            .await
+            .map_err(|e| format!("applying migration {}: {:?}", migration.version, e))?;
+        tx.execute(
+            include_sql!("schema/add.sql"),
+            params![
+                migration.version,
+                migration.name,
+                expected,
+                Utc::now().timestamp()
+            ],
+        )
+        .await
+        .map_err(|e| format!("recording migration {}: {:?}", migration.version, e))?;
+        tx.commit()
+            .await
+            .map_err(|e| format!("committing migration {}: {:?}", migration.version, e))?;
+    }
+    Ok(())
+}
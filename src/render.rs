@@ -0,0 +1,371 @@
+use cairo::{Context, Format, ImageSurface};
+use chrono::prelude::*;
+
+//Line colours, picked in order for each series
+const PALETTE: &[(f64, f64, f64)] = &[
+    (0.5, 0.5, 1.0),
+    (1.0, 0.5, 0.5),
+    (0.5, 1.0, 0.5),
+    (1.0, 0.8, 0.3),
+    (0.8, 0.5, 1.0),
+    (0.3, 0.9, 0.9),
+    (1.0, 0.5, 0.8),
+    (0.7, 0.7, 0.7),
+];
+
+pub struct Series {
+    pub label: String,
+    //Unix timestamp and value
+    pub points: Vec<(i64, f64)>,
+}
+
+pub fn background(cairo: &Context, width: f64, height: f64) -> Result<(), cairo::Error> {
+    #[allow(clippy::unnecessary_cast)]
+    cairo.set_source_rgba(
+        0x2E as f64 / 0xFF as f64,
+        0x2E as f64 / 0xFF as f64,
+        0x2E as f64 / 0xFF as f64,
+        1.0,
+    );
+    cairo.rectangle(0.0, 0.0, width, height);
+    cairo.fill()
+}
+
+pub fn palette_colour(index: usize) -> (f64, f64, f64) {
+    PALETTE[index % PALETTE.len()]
+}
+
+pub fn surface_to_png(surface: &ImageSurface) -> Result<Vec<u8>, String> {
+    let mut rendered_image = Vec::new();
+    surface
+        .write_to_png(&mut rendered_image)
+        .map_err(|e| format!("Writing as PNG failed: {:?}", e))?;
+    Ok(rendered_image)
+}
+
+//Rounds up to a number which makes for nice axis labels
+fn nice_max(value: f64) -> f64 {
+    if value <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(value.log10().floor());
+    for step in [1.0, 2.0, 2.5, 5.0, 10.0] {
+        if value <= step * magnitude {
+            return step * magnitude;
+        }
+    }
+    10.0 * magnitude
+}
+
+fn bar_chart_to_surface(
+    bars: &[(String, i64)],
+    label_font: &str,
+) -> Result<ImageSurface, cairo::Error> {
+    //Initialize image with some constants
+    let padding = 20.0;
+    let thickness = 25.0;
+    let y_shift = 30.0;
+    let height_unit = 800.0 / f64::from(bars[0].1 as i32); //Limit height of bar to 800 and have each other bar be a representation of that
+    let width = (padding + thickness) * bars.len() as f64 + 50.0;
+    let height = (f64::from(bars[0].1 as i32) * height_unit + 70.0 + y_shift).ceil();
+    let font_size = 15.0;
+
+    let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32)?;
+    let cairo = Context::new(&surface)?;
+    background(&cairo, width, height)?;
+    cairo.set_font_size(font_size);
+
+    for (index, (label, value)) in bars.iter().enumerate() {
+        cairo.set_source_rgba(0.5, 0.5, 1.0, 1.0);
+        let x_pos = (thickness + padding) * index as f64 + padding;
+        let bar_height = f64::from(*value as i32) * height_unit;
+        cairo.rectangle(x_pos, padding + y_shift, thickness, bar_height);
+        cairo.fill()?;
+
+        cairo.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+
+        //Number
+        cairo.select_font_face("Hack", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+        let number_y_position = bar_height + 5.0 + y_shift + padding + font_size;
+        cairo.move_to(x_pos, number_y_position);
+        cairo.show_text(&value.to_string())?;
+
+        //Stagger the text in order make the labels more readable
+        cairo.select_font_face(
+            label_font,
+            cairo::FontSlant::Normal,
+            cairo::FontWeight::Normal,
+        );
+        let text_y_position = font_size
+            + if index % 2 == 0 {
+                y_shift
+            } else {
+                y_shift / 2.0
+            };
+        cairo.move_to(x_pos, text_y_position);
+        cairo.show_text(label)?;
+    }
+    Ok(surface)
+}
+
+//Renders labelled bars as a PNG. They should be sorted with the largest first.
+pub fn bar_chart(bars: &[(String, i64)], label_font: &str) -> Result<Vec<u8>, String> {
+    let surface =
+        bar_chart_to_surface(bars, label_font).map_err(|e| format!("Cairo error: {:?}", e))?;
+    surface_to_png(&surface)
+}
+
+fn line_chart_to_surface(title: &str, series: &[Series]) -> Result<ImageSurface, cairo::Error> {
+    let width = 1600.0;
+    let height = 900.0;
+    let margin_left = 100.0;
+    let margin_right = 50.0;
+    let margin_top = 80.0;
+    let margin_bottom = 80.0;
+    let legend_line_height = 30.0;
+    let font_size = 20.0;
+    let y_ticks = 5;
+    let x_ticks = 6;
+
+    let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32)?;
+    let cairo = Context::new(&surface)?;
+    background(&cairo, width, height)?;
+    cairo.select_font_face("Hack", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+
+    let all_points = || series.iter().flat_map(|s| s.points.iter());
+    let min_x = all_points().map(|p| p.0).min().unwrap_or(0);
+    let max_x = all_points().map(|p| p.0).max().unwrap_or(1).max(min_x + 1);
+    let max_y = nice_max(all_points().map(|p| p.1).fold(0.0, f64::max));
+    //Only go below zero when a series does
+    let lowest = all_points().map(|p| p.1).fold(0.0, f64::min);
+    let min_y = if lowest < 0.0 {
+        -nice_max(-lowest)
+    } else {
+        0.0
+    };
+
+    let plot_width = width - margin_left - margin_right;
+    let plot_height = height - margin_top - margin_bottom;
+    let to_x = |x: i64| margin_left + (x - min_x) as f64 / (max_x - min_x) as f64 * plot_width;
+    let to_y = |y: f64| margin_top + plot_height - (y - min_y) / (max_y - min_y) * plot_height;
+
+    //Title
+    cairo.set_source_rgb(1.0, 1.0, 1.0);
+    cairo.set_font_size(font_size * 1.5);
+    let extents = cairo.text_extents(title)?;
+    cairo.move_to((width - extents.width()) / 2.0, margin_top / 2.0 + 10.0);
+    cairo.show_text(title)?;
+
+    //Grid and y labels
+    cairo.set_font_size(font_size);
+    cairo.set_line_width(1.0);
+    for tick in 0..=y_ticks {
+        let value = min_y + (max_y - min_y) * f64::from(tick) / f64::from(y_ticks);
+        let y = to_y(value);
+        cairo.set_source_rgba(1.0, 1.0, 1.0, 0.2);
+        cairo.move_to(margin_left, y);
+        cairo.line_to(width - margin_right, y);
+        cairo.stroke()?;
+
+        let text = if max_y - min_y >= 10.0 {
+            format!("{:.0}", value)
+        } else {
+            format!("{:.1}", value)
+        };
+        let extents = cairo.text_extents(&text)?;
+        cairo.set_source_rgb(1.0, 1.0, 1.0);
+        cairo.move_to(
+            margin_left - extents.width() - 10.0,
+            y + extents.height() / 2.0,
+        );
+        cairo.show_text(&text)?;
+    }
+
+    //X labels as dates
+    for tick in 0..=x_ticks {
+        let timestamp = min_x + (max_x - min_x) * tick / x_ticks;
+        let text = Local
+            .timestamp_opt(timestamp, 0)
+            .unwrap()
+            .format("%e %b %Y")
+            .to_string();
+        let extents = cairo.text_extents(&text)?;
+        let x = (to_x(timestamp) - extents.width() / 2.0)
+            .max(0.0)
+            .min(width - extents.width());
+        cairo.move_to(x, height - margin_bottom / 2.0);
+        cairo.show_text(&text)?;
+    }
+
+    //The lines themselves
+    cairo.set_line_width(3.0);
+    for (index, s) in series.iter().enumerate() {
+        let (r, g, b) = palette_colour(index);
+        cairo.set_source_rgb(r, g, b);
+        for (i, (x, y)) in s.points.iter().enumerate() {
+            if i == 0 {
+                cairo.move_to(to_x(*x), to_y(*y));
+            } else {
+                cairo.line_to(to_x(*x), to_y(*y));
+            }
+        }
+        cairo.stroke()?;
+
+        //Legend, only needed when there is more than one line
+        if series.len() > 1 {
+            let legend_y = margin_top + 20.0 + index as f64 * legend_line_height;
+            cairo.rectangle(margin_left + 20.0, legend_y - font_size / 2.0, 30.0, 6.0);
+            cairo.fill()?;
+            cairo.set_source_rgb(1.0, 1.0, 1.0);
+            cairo.move_to(margin_left + 60.0, legend_y);
+            cairo.show_text(&s.label)?;
+        }
+    }
+
+    Ok(surface)
+}
+
+//Renders one or more series over time as a PNG
+pub fn line_chart(title: &str, series: &[Series]) -> Result<Vec<u8>, String> {
+    let surface =
+        line_chart_to_surface(title, series).map_err(|e| format!("Cairo error: {:?}", e))?;
+    surface_to_png(&surface)
+}
+
+fn podium_to_surface(title: &str, places: &[(String, i64)]) -> Result<ImageSurface, cairo::Error> {
+    let width = 900.0;
+    let height = 600.0;
+    let step_width = 250.0;
+    let base = height - 40.0;
+    let font_size = 24.0;
+    //Second place stands left of the winner and third place right, each lower than the last
+    let steps = [(1, 0.0, 300.0), (0, -1.0, 220.0), (2, 1.0, 160.0)];
+
+    let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32)?;
+    let cairo = Context::new(&surface)?;
+    background(&cairo, width, height)?;
+    cairo.select_font_face("Hack", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+
+    cairo.set_source_rgb(1.0, 1.0, 1.0);
+    cairo.set_font_size(font_size * 1.5);
+    let extents = cairo.text_extents(title)?;
+    cairo.move_to((width - extents.width()) / 2.0, 50.0);
+    cairo.show_text(title)?;
+
+    cairo.set_font_size(font_size);
+    for (place, offset, step_height) in steps {
+        let (name, points) = match places.get(place) {
+            Some(p) => p,
+            None => continue,
+        };
+        let x = width / 2.0 + offset * step_width - step_width / 2.0;
+        let top = base - step_height;
+
+        let (r, g, b) = palette_colour(place);
+        cairo.set_source_rgb(r, g, b);
+        cairo.rectangle(x, top, step_width, step_height);
+        cairo.fill()?;
+
+        cairo.set_source_rgb(1.0, 1.0, 1.0);
+        let number = (place + 1).to_string();
+        let extents = cairo.text_extents(&number)?;
+        cairo.move_to(x + (step_width - extents.width()) / 2.0, top + 40.0);
+        cairo.show_text(&number)?;
+
+        let label = format!("{} ({})", name, points);
+        let extents = cairo.text_extents(&label)?;
+        let label_x = (x + (step_width - extents.width()) / 2.0).max(5.0);
+        cairo.move_to(label_x, top - 15.0);
+        cairo.show_text(&label)?;
+    }
+
+    Ok(surface)
+}
+
+//Renders the top three places of a competition as a PNG, best first
+pub fn podium(title: &str, places: &[(String, i64)]) -> Result<Vec<u8>, String> {
+    let surface = podium_to_surface(title, places).map_err(|e| format!("Cairo error: {:?}", e))?;
+    surface_to_png(&surface)
+}
+
+fn heatmap_to_surface(
+    title: &str,
+    labels: &[String],
+    cells: &[Vec<i64>],
+) -> Result<ImageSurface, cairo::Error> {
+    let cell_size = 60.0;
+    let label_space = 250.0;
+    let margin = 30.0;
+    let title_height = 60.0;
+    let font_size = 18.0;
+    let cells_width = cell_size * labels.len() as f64;
+    let width = label_space + cells_width + margin;
+    let height = title_height + label_space + cells_width + margin;
+    let top = title_height + label_space;
+
+    let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32)?;
+    let cairo = Context::new(&surface)?;
+    background(&cairo, width, height)?;
+    cairo.select_font_face("Hack", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+
+    cairo.set_source_rgb(1.0, 1.0, 1.0);
+    cairo.set_font_size(font_size * 1.5);
+    let extents = cairo.text_extents(title)?;
+    cairo.move_to(
+        ((width - extents.width()) / 2.0).max(5.0),
+        title_height / 2.0 + 10.0,
+    );
+    cairo.show_text(title)?;
+
+    //Rows on the left and columns above, written upwards
+    cairo.set_font_size(font_size);
+    for (index, label) in labels.iter().enumerate() {
+        let offset = cell_size * index as f64 + cell_size / 2.0;
+        let extents = cairo.text_extents(label)?;
+        cairo.move_to(
+            (label_space - extents.width() - 10.0).max(5.0),
+            top + offset + extents.height() / 2.0,
+        );
+        cairo.show_text(label)?;
+
+        cairo.save()?;
+        cairo.move_to(label_space + offset + extents.height() / 2.0, top - 10.0);
+        cairo.rotate(-std::f64::consts::FRAC_PI_2);
+        cairo.show_text(label)?;
+        cairo.restore()?;
+    }
+
+    //Cells get more intense the higher their count is
+    let max = cells.iter().flatten().copied().max().unwrap_or(0).max(1);
+    let (r, g, b) = palette_colour(1);
+    for (row, values) in cells.iter().enumerate() {
+        for (column, value) in values.iter().enumerate() {
+            let x = label_space + cell_size * column as f64;
+            let y = top + cell_size * row as f64;
+            let intensity = (*value).max(0) as f64 / max as f64;
+            cairo.set_source_rgba(r, g, b, 0.1 + 0.9 * intensity);
+            cairo.rectangle(x + 1.0, y + 1.0, cell_size - 2.0, cell_size - 2.0);
+            cairo.fill()?;
+
+            if *value != 0 {
+                let text = value.to_string();
+                let extents = cairo.text_extents(&text)?;
+                cairo.set_source_rgb(1.0, 1.0, 1.0);
+                cairo.move_to(
+                    x + (cell_size - extents.width()) / 2.0,
+                    y + (cell_size + extents.height()) / 2.0,
+                );
+                cairo.show_text(&text)?;
+            }
+        }
+    }
+
+    Ok(surface)
+}
+
+//Renders a square matrix of counts between the same labels as a PNG, with cells[row][column]
+pub fn heatmap(title: &str, labels: &[String], cells: &[Vec<i64>]) -> Result<Vec<u8>, String> {
+    let surface =
+        heatmap_to_surface(title, labels, cells).map_err(|e| format!("Cairo error: {:?}", e))?;
+    surface_to_png(&surface)
+}
@@ -1,5 +1,7 @@
+pub mod callbackquery;
 pub mod chat;
 pub mod message;
+pub mod reaction;
 pub mod update;
 pub mod user;
 
@@ -9,7 +11,7 @@ use reqwest::{multipart, Client, Url};
 use serde::Deserialize;
 use std::fmt;
 use update::UpdateStream;
-use user::User;
+use user::{ChatMember, User};
 
 #[derive(Deserialize, Debug)]
 struct ApiChat {
@@ -26,9 +28,58 @@ struct ApiUpdate {
     edited_message: Option<ApiMessage>,
     channel_post: Option<ApiMessage>,
     edited_channel_post: Option<ApiMessage>,
+    callback_query: Option<ApiCallbackQuery>,
+    message_reaction: Option<ApiMessageReaction>,
+    message_reaction_count: Option<ApiMessageReactionCount>,
+    chat_member: Option<ApiChatMemberUpdated>,
+    my_chat_member: Option<ApiChatMemberUpdated>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiChatMemberUpdated {
+    chat: ApiChat,
+    from: User,
+    date: i64,
+    old_chat_member: ChatMember,
+    new_chat_member: ChatMember,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiMessageReaction {
+    chat: ApiChat,
+    message_id: i64,
+    //Missing when reacting anonymously
+    user: Option<User>,
+    date: i64,
+    old_reaction: Vec<reaction::ReactionType>,
+    new_reaction: Vec<reaction::ReactionType>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiMessageReactionCount {
+    chat: ApiChat,
+    message_id: i64,
+    date: i64,
+    reactions: Vec<ApiReactionCount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiReactionCount {
+    #[serde(rename = "type")]
+    kind: reaction::ReactionType,
+    total_count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiCallbackQuery {
+    id: String,
+    from: User,
+    message: Option<ApiMessage>,
+    data: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
+#[allow(dead_code)] //Not every field is used yet
 pub struct Sticker {
     pub file_id: String,
     pub width: u32,
@@ -36,6 +87,87 @@ pub struct Sticker {
     pub emoji: Option<String>,
     pub set_name: Option<String>,
     pub file_size: Option<usize>,
+    //Animated stickers are .tgs files and video stickers are .webm files, neither can be
+    //decoded, so their thumbnail is used instead
+    #[serde(default)]
+    pub is_animated: bool,
+    #[serde(default)]
+    pub is_video: bool,
+    #[serde(alias = "thumb")]
+    pub thumbnail: Option<PhotoSize>,
+}
+
+impl Sticker {
+    //File id of a still image of this sticker, if there is one
+    pub fn image_file_id(&self) -> Option<&str> {
+        if self.is_animated || self.is_video {
+            self.thumbnail.as_ref().map(|t| t.file_id.as_str())
+        } else {
+            Some(&self.file_id)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StickerSet {
+    pub title: String,
+    pub stickers: Vec<Sticker>,
+    //Only set when the pack has a thumbnail of its own, in the same format as its stickers
+    #[serde(alias = "thumb")]
+    pub thumbnail: Option<PhotoSize>,
+}
+
+impl StickerSet {
+    //File id of a still image to show for the pack, if there is one
+    pub fn image_file_id(&self) -> Option<&str> {
+        let first = self.stickers.first();
+        let animated = first.is_some_and(|s| s.is_animated || s.is_video);
+        match &self.thumbnail {
+            Some(t) if !animated => Some(t.file_id.as_str()),
+            _ => first.and_then(|s| s.image_file_id()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PhotoSize {
+    pub file_id: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MessageEntity {
+    #[serde(rename = "type")]
+    pub kind: String,
+    //Offset and length are counted in UTF-16 code units
+    pub offset: usize,
+    pub length: usize,
+    pub url: Option<String>,
+    pub custom_emoji_id: Option<String>,
+}
+
+impl MessageEntity {
+    //Gets the part of the message text this entity covers
+    pub fn text<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let end = self.offset + self.length;
+        let mut units = 0;
+        let mut start = None;
+        for (index, c) in text.char_indices() {
+            if units == self.offset {
+                start = Some(index);
+            }
+            if units == end {
+                return start.map(|s| &text[s..index]);
+            }
+            units += c.len_utf16();
+        }
+        if units == end {
+            start.map(|s| &text[s..])
+        } else {
+            None
+        }
+    }
 }
 
 impl fmt::Display for Sticker {
@@ -62,7 +194,62 @@ struct ApiMessage {
     forward_from: Option<User>,
     reply_to_message: Option<Box<ApiMessage>>,
     sticker: Option<Sticker>,
+    photo: Option<Vec<PhotoSize>>,
+    #[serde(default)]
+    entities: Vec<MessageEntity>,
     chat: ApiChat,
+    message_thread_id: Option<i64>,
+    //Replies outside of forums have a thread id too, this tells the two apart
+    #[serde(default)]
+    is_topic_message: bool,
+    forum_topic_created: Option<ApiForumTopic>,
+    forum_topic_edited: Option<ApiForumTopic>,
+    //A group became a supergroup. The old group gets the first and the supergroup the second.
+    migrate_to_chat_id: Option<i64>,
+    migrate_from_chat_id: Option<i64>,
+    new_chat_members: Option<Vec<User>>,
+    left_chat_member: Option<User>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiForumTopic {
+    //Only set on edits when the name changed
+    name: Option<String>,
+}
+
+//Where to send a message: a chat, and in forum supergroups the topic within it
+#[derive(Clone, Copy, Debug)]
+pub struct ChatTarget {
+    pub chat_id: i64,
+    pub thread_id: Option<i64>,
+}
+
+impl From<i64> for ChatTarget {
+    fn from(chat_id: i64) -> Self {
+        Self {
+            chat_id,
+            thread_id: None,
+        }
+    }
+}
+
+//Answers go to the topic the message was sent in
+impl From<&Message> for ChatTarget {
+    fn from(msg: &Message) -> Self {
+        Self {
+            chat_id: msg.chat.id,
+            thread_id: msg.thread_id,
+        }
+    }
+}
+
+impl ChatTarget {
+    fn add_to(&self, json: &mut serde_json::Value) {
+        json["chat_id"] = self.chat_id.into();
+        if let Some(thread_id) = self.thread_id {
+            json["message_thread_id"] = thread_id.into();
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -79,6 +266,16 @@ struct Response<T> {
     result: Option<T>,
     ok: bool,
     description: Option<String>,
+    error_code: Option<i64>,
+}
+
+//Why a chat member couldn't be looked up
+#[derive(Debug)]
+pub enum ChatMemberError {
+    //The user has never been in the chat, or the bot isn't in it anymore
+    NotFound,
+    //The request failed or was refused, like when being rate limited
+    Failed(String),
 }
 
 impl Telegram {
@@ -133,8 +330,13 @@ impl Telegram {
         Url::parse(&format!("{}/{}", self.base_url, endpoint)).unwrap()
     }
 
-    async fn send_message_raw(&self, serialized: serde_json::Value) -> Result<Message, String> {
+    async fn send_message_raw(
+        &self,
+        chat: ChatTarget,
+        mut serialized: serde_json::Value,
+    ) -> Result<Message, String> {
         let url = self.get_url("sendMessage");
+        chat.add_to(&mut serialized);
 
         let reply = self
             .client
@@ -158,57 +360,53 @@ impl Telegram {
     pub async fn reply_to(
         &self,
         msg_id: i64,
-        chat_id: i64,
+        chat: impl Into<ChatTarget>,
         text: String,
     ) -> Result<Message, String> {
         let json = serde_json::json!({
             "reply_to_message_id": msg_id,
-            "chat_id": chat_id,
             "text": text,
         });
-        self.send_message_raw(json).await
+        self.send_message_raw(chat.into(), json).await
     }
 
     pub async fn reply_with_markup(
         &self,
         msg_id: i64,
-        chat_id: i64,
+        chat: impl Into<ChatTarget>,
         text: String,
         markup: serde_json::Value,
     ) -> Result<Message, String> {
         let json = serde_json::json!({
             "reply_to_message_id": msg_id,
-            "chat_id": chat_id,
             "text": text,
             "disable_notification": true,
             "reply_markup": markup
         });
-        self.send_message_raw(json).await
+        self.send_message_raw(chat.into(), json).await
     }
 
     pub async fn send_message_silently_with_markdown(
         &self,
-        chat_id: i64,
+        chat: impl Into<ChatTarget>,
         text: String,
     ) -> Result<Message, String> {
         let json = serde_json::json!({
             "text": text,
-            "chat_id": chat_id,
             "disable_notification": true,
             "parse_mode": "Markdown"
         });
-        self.send_message_raw(json).await
+        self.send_message_raw(chat.into(), json).await
     }
 
     pub async fn reply_and_close_keyboard(
         &self,
         msg_id: i64,
-        chat_id: i64,
+        chat: impl Into<ChatTarget>,
         text: String,
     ) -> Result<Message, String> {
         let json = serde_json::json!({
             "reply_to_message_id": msg_id,
-            "chat_id": chat_id,
             "text": text,
             "disable_notification": true,
             "reply_markup": {
@@ -216,16 +414,104 @@ impl Telegram {
                 "selective": true
             }
         });
-        self.send_message_raw(json).await
+        self.send_message_raw(chat.into(), json).await
     }
 
-    pub async fn send_message_silent(&self, chat_id: i64, text: String) -> Result<Message, String> {
+    pub async fn send_message_silent(
+        &self,
+        chat: impl Into<ChatTarget>,
+        text: String,
+    ) -> Result<Message, String> {
         let json = serde_json::json!({
-            "chat_id": chat_id,
             "text": text,
             "disable_notification": true,
         });
-        self.send_message_raw(json).await
+        self.send_message_raw(chat.into(), json).await
+    }
+
+    pub async fn send_message_silently_with_html(
+        &self,
+        chat: impl Into<ChatTarget>,
+        text: String,
+        markup: Option<serde_json::Value>,
+    ) -> Result<Message, String> {
+        let mut json = serde_json::json!({
+            "text": text,
+            "disable_notification": true,
+            "disable_web_page_preview": true,
+            "parse_mode": "HTML"
+        });
+        if let Some(markup) = markup {
+            json["reply_markup"] = markup;
+        }
+        self.send_message_raw(chat.into(), json).await
+    }
+
+    pub async fn edit_message_with_html(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        text: String,
+        markup: Option<serde_json::Value>,
+    ) -> Result<(), String> {
+        let url = self.get_url("editMessageText");
+        let mut json = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "text": text,
+            "disable_web_page_preview": true,
+            "parse_mode": "HTML"
+        });
+        if let Some(markup) = markup {
+            json["reply_markup"] = markup;
+        }
+
+        //Returns the edited message, which there's no use for
+        let reply = self
+            .client
+            .get(url)
+            .json(&json)
+            .send()
+            .await
+            .map_err(|e| format!("editing message: {}", e))?
+            .json::<Response<serde_json::Value>>()
+            .await
+            .map_err(|e| format!("deserializing edit response: {}", e))?;
+
+        if reply.ok {
+            Ok(())
+        } else {
+            Err(reply.description.unwrap())
+        }
+    }
+
+    pub async fn answer_callback_query(
+        &self,
+        query_id: &str,
+        text: Option<String>,
+    ) -> Result<(), String> {
+        let url = self.get_url("answerCallbackQuery");
+        let json = serde_json::json!({
+            "callback_query_id": query_id,
+            "text": text,
+        });
+
+        let reply = self
+            .client
+            .get(url)
+            .json(&json)
+            .send()
+            .await
+            .map_err(|e| format!("answering callback query: {}", e))?
+            .json::<Response<bool>>()
+            .await
+            .map_err(|e| format!("deserializing callback query answer: {}", e))?;
+
+        if reply.ok {
+            Ok(())
+        } else {
+            Err(reply.description.unwrap())
+        }
     }
 
     // pub async fn send_message(&self, chat_id: i64, text: String) -> Result<Message, ()> {
@@ -346,22 +632,30 @@ impl Telegram {
     //Send a message in png format, panics if data is not a valid PNG image
     pub async fn send_png_lossless(
         &self,
-        chat_id: i64,
+        chat: impl Into<ChatTarget>,
         data: Vec<u8>,
         caption: Option<String>,
         silent: bool,
     ) -> Result<Message, String> {
+        let chat = chat.into();
         let url = self.get_url("sendDocument");
         let form = multipart::Form::new()
             .part(
                 "document",
                 multipart::Part::bytes(data).file_name("image.png"),
             )
-            .part("chat_id", multipart::Part::text(chat_id.to_string()))
+            .part("chat_id", multipart::Part::text(chat.chat_id.to_string()))
             .part(
                 "disable_notification",
                 multipart::Part::text(silent.to_string()),
             );
+        let form = match chat.thread_id {
+            Some(thread_id) => form.part(
+                "message_thread_id",
+                multipart::Part::text(thread_id.to_string()),
+            ),
+            None => form,
+        };
 
         let form = if let Some(c) = caption {
             form.part("caption", multipart::Part::text(c))
@@ -387,33 +681,58 @@ impl Telegram {
         }
     }
 
-    pub async fn get_chat_member(&self, chat_id: i64, user_id: i64) -> Result<User, ()> {
+    pub async fn get_chat_member(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+    ) -> Result<ChatMember, ChatMemberError> {
         let url = self.get_url("getChatMember");
         let json = serde_json::json!({
             "chat_id": chat_id,
             "user_id": user_id,
         });
 
-        #[derive(Deserialize)]
-        struct RespResult {
-            user: User,
+        let reply = self
+            .client
+            .get(url)
+            .json(&json)
+            .send()
+            .await
+            .map_err(|e| ChatMemberError::Failed(format!("getting chat member: {}", e)))?
+            .json::<Response<ChatMember>>()
+            .await
+            .map_err(|e| ChatMemberError::Failed(format!("deserializing chat member: {}", e)))?;
+
+        match (reply.ok, reply.result) {
+            (true, Some(member)) => Ok(member),
+            //Bad requests are about users or chats Telegram doesn't know
+            _ if reply.error_code == Some(400) => Err(ChatMemberError::NotFound),
+            _ => Err(ChatMemberError::Failed(
+                reply.description.unwrap_or_default(),
+            )),
         }
+    }
 
-        #[derive(Deserialize)]
-        struct Response {
-            result: RespResult,
-        }
+    pub async fn get_sticker_set(&self, name: &str) -> Result<StickerSet, String> {
+        let url = self.get_url("getStickerSet");
+        let json = serde_json::json!({ "name": name });
 
-        self.client
+        let reply = self
+            .client
             .get(url)
             .json(&json)
             .send()
             .await
-            .unwrap()
-            .json::<Response>()
+            .map_err(|e| format!("getting sticker set: {}", e))?
+            .json::<Response<StickerSet>>()
             .await
-            .map(|u| u.result.user)
-            .map_err(|_| ())
+            .map_err(|e| format!("deserializing sticker set: {}", e))?;
+
+        if reply.ok {
+            Ok(reply.result.unwrap())
+        } else {
+            Err(reply.description.unwrap())
+        }
     }
 
     pub async fn delete_message(&self, chat_id: i64, message_id: i64) {
@@ -0,0 +1,22 @@
+use super::{message::Message, user::User, ApiCallbackQuery};
+use std::convert::From;
+
+#[derive(Clone, Debug)]
+pub struct CallbackQuery {
+    pub id: String,
+    pub from: User,
+    pub message: Option<Message>,
+    pub data: Option<String>,
+}
+
+impl From<ApiCallbackQuery> for CallbackQuery {
+    fn from(query: ApiCallbackQuery) -> Self {
+        Self {
+            id: query.id,
+            from: query.from,
+            //Messages which are too old to be accessed are sent without a sender
+            message: query.message.filter(|m| m.from.is_some()).map(|m| m.into()),
+            data: query.data,
+        }
+    }
+}
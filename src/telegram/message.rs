@@ -1,4 +1,4 @@
-use super::{chat::Chat, user::User, ApiMessage, Sticker};
+use super::{chat::Chat, user::User, ApiMessage, MessageEntity, PhotoSize, Sticker};
 use std::convert::From;
 use std::fmt;
 
@@ -9,29 +9,23 @@ pub struct Message {
     pub date: i64,
     pub data: MessageData,
     pub chat: Chat,
+    //The forum topic the message was sent in, None outside of forums and in the General topic
+    pub thread_id: Option<i64>,
+    //Formatting, links, custom emoji and such in the text
+    pub entities: Vec<MessageEntity>,
 }
 
 impl From<ApiMessage> for Message {
     fn from(message: ApiMessage) -> Self {
         let date = message.date;
-        let chat = message.chat.into();
-        let data = if let Some(msg) = message.reply_to_message {
-            //This will not cause infinite recursion because the messages Telegram sends as
-            //reply_to_message doesn't contain another replied to message
-            let data = if let Some(text) = message.text {
-                if let Some(forwarded) = message.forward_from {
-                    MessageData::Forward(forwarded, text)
-                } else {
-                    MessageData::Text(text)
-                }
-            } else if let Some(sticker) = message.sticker {
-                MessageData::Sticker(sticker)
-            } else {
-                MessageData::Other
-            };
-            let converted = (*msg).into();
-            MessageData::Reply(Box::new(data), Box::new(converted))
-        } else if let Some(text) = message.text {
+        let chat: Chat = message.chat.into();
+        let thread_id = if message.is_topic_message {
+            message.message_thread_id
+        } else {
+            None
+        };
+
+        let data = if let Some(text) = message.text {
             if let Some(forwarded) = message.forward_from {
                 MessageData::Forward(forwarded, text)
             } else {
@@ -39,16 +33,44 @@ impl From<ApiMessage> for Message {
             }
         } else if let Some(sticker) = message.sticker {
             MessageData::Sticker(sticker)
+        } else if let Some(photo) = message.photo {
+            MessageData::Photo(photo)
+        } else if let Some(name) = message.forum_topic_created.and_then(|t| t.name) {
+            MessageData::TopicCreated(name)
+        } else if let Some(name) = message.forum_topic_edited.and_then(|t| t.name) {
+            MessageData::TopicEdited(name)
+        } else if let Some(users) = message.new_chat_members {
+            MessageData::MembersJoined(users)
+        } else if let Some(user) = message.left_chat_member {
+            MessageData::MemberLeft(user)
+        } else if let Some(to) = message.migrate_to_chat_id {
+            MessageData::ChatMigrated { from: chat.id, to }
+        } else if let Some(from) = message.migrate_from_chat_id {
+            MessageData::ChatMigrated { from, to: chat.id }
         } else {
             MessageData::Other
         };
 
+        //Messages in a forum topic are sent as replies to the message which created the topic,
+        //even when they don't reply to anything
+        let data = match message.reply_to_message {
+            Some(msg) if Some(msg.id) != thread_id => {
+                //This will not cause infinite recursion because the messages Telegram sends as
+                //reply_to_message doesn't contain another replied to message
+                let converted = (*msg).into();
+                MessageData::Reply(Box::new(data), Box::new(converted))
+            }
+            _ => data,
+        };
+
         Self {
             id: message.id,
             from: message.from.unwrap(),
             date,
             data,
             chat,
+            thread_id,
+            entities: message.entities,
         }
     }
 }
@@ -67,6 +89,15 @@ pub enum MessageData {
     Text(String),
     Forward(User, String),
     Sticker(Sticker),
+    //Every size Telegram has of the photo, smallest first
+    Photo(Vec<PhotoSize>),
+    //A forum topic was created or renamed
+    TopicCreated(String),
+    TopicEdited(String),
+    MembersJoined(Vec<User>),
+    MemberLeft(User),
+    //The group got a new chat id by becoming a supergroup
+    ChatMigrated { from: i64, to: i64 },
     Reply(Box<MessageData>, Box<Message>),
     //Unsupported
     Other,
@@ -79,6 +110,17 @@ impl fmt::Display for MessageData {
             MessageData::Forward(u, s) => write!(f, "[Forwarded from {}]: {}", u, s),
             MessageData::Reply(this, other) => write!(f, "[Reply to {}]: {}", other.id, this),
             MessageData::Sticker(s) => write!(f, "[{}]", s),
+            MessageData::Photo(_) => write!(f, "[Photo]"),
+            MessageData::TopicCreated(name) => write!(f, "[Created topic {}]", name),
+            MessageData::TopicEdited(name) => write!(f, "[Renamed topic to {}]", name),
+            MessageData::MembersJoined(users) => {
+                let names: Vec<String> = users.iter().map(|u| u.to_string()).collect();
+                write!(f, "[Joined: {}]", names.join(", "))
+            }
+            MessageData::MemberLeft(user) => write!(f, "[Left: {}]", user),
+            MessageData::ChatMigrated { from, to } => {
+                write!(f, "[Chat migrated from {} to {}]", from, to)
+            }
             MessageData::Other => write!(f, "[Unsupported]"),
         }
     }
@@ -0,0 +1,95 @@
+use super::{chat::Chat, user::User, ApiMessageReaction, ApiMessageReactionCount};
+use crate::tokenizer::normalize_emoji;
+use serde::Deserialize;
+use std::convert::From;
+use std::fmt;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReactionType {
+    Emoji {
+        emoji: String,
+    },
+    CustomEmoji {
+        custom_emoji_id: String,
+    },
+    Paid,
+    //Reaction types added to the API later on
+    #[serde(other)]
+    Unknown,
+}
+
+//How reactions are stored in the database
+impl fmt::Display for ReactionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReactionType::Emoji { emoji } => write!(f, "{}", normalize_emoji(emoji)),
+            ReactionType::CustomEmoji { custom_emoji_id } => {
+                write!(f, "custom:{}", custom_emoji_id)
+            }
+            ReactionType::Paid => write!(f, "paid"),
+            ReactionType::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+//A user changing their reactions to a message
+#[derive(Clone, Debug)]
+pub struct MessageReaction {
+    pub chat: Chat,
+    pub message_id: i64,
+    pub user: User,
+    pub date: i64,
+    pub added: Vec<ReactionType>,
+    pub removed: Vec<ReactionType>,
+}
+
+impl MessageReaction {
+    //Anonymous reactions only show up in reaction counts
+    pub(super) fn from_api(from: ApiMessageReaction) -> Option<Self> {
+        let added = from
+            .new_reaction
+            .iter()
+            .filter(|r| !from.old_reaction.contains(r))
+            .cloned()
+            .collect();
+        let removed = from
+            .old_reaction
+            .iter()
+            .filter(|r| !from.new_reaction.contains(r))
+            .cloned()
+            .collect();
+        Some(Self {
+            chat: from.chat.into(),
+            message_id: from.message_id,
+            user: from.user?,
+            date: from.date,
+            added,
+            removed,
+        })
+    }
+}
+
+//The total reactions to a message, sent for chats where reactions are anonymous
+#[derive(Clone, Debug)]
+pub struct MessageReactionCount {
+    pub chat: Chat,
+    pub message_id: i64,
+    pub date: i64,
+    pub reactions: Vec<(ReactionType, i64)>,
+}
+
+impl From<ApiMessageReactionCount> for MessageReactionCount {
+    fn from(from: ApiMessageReactionCount) -> Self {
+        Self {
+            chat: from.chat.into(),
+            message_id: from.message_id,
+            date: from.date,
+            reactions: from
+                .reactions
+                .into_iter()
+                .map(|r| (r.kind, r.total_count))
+                .collect(),
+        }
+    }
+}
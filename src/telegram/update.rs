@@ -1,15 +1,29 @@
 mod updatestream;
 pub use updatestream::UpdateStream;
 
-use super::{message::Message, ApiUpdate};
+use super::{
+    callbackquery::CallbackQuery,
+    message::Message,
+    reaction::{MessageReaction, MessageReactionCount},
+    user::ChatMemberUpdated,
+    ApiUpdate,
+};
 use std::convert::TryFrom;
 
 #[derive(Debug)]
+#[allow(dead_code)] //Channel posts are only logged
 pub enum Update {
     Message(Message),
     MessageEdited(Message),
     ChannelPost(Message),
     ChannelPostEdited(Message),
+    CallbackQuery(CallbackQuery),
+    Reaction(MessageReaction),
+    ReactionCount(MessageReactionCount),
+    //Some user's membership changed, only sent when the bot is an administrator
+    ChatMember(ChatMemberUpdated),
+    //The bot's own membership changed
+    MyChatMember(ChatMemberUpdated),
 }
 
 impl TryFrom<ApiUpdate> for Update {
@@ -24,6 +38,18 @@ impl TryFrom<ApiUpdate> for Update {
             Ok(Update::ChannelPost(msg.into()))
         } else if let Some(msg) = from.edited_channel_post {
             Ok(Update::ChannelPostEdited(msg.into()))
+        } else if let Some(query) = from.callback_query {
+            Ok(Update::CallbackQuery(query.into()))
+        } else if let Some(reaction) = from.message_reaction {
+            MessageReaction::from_api(reaction)
+                .map(Update::Reaction)
+                .ok_or(())
+        } else if let Some(count) = from.message_reaction_count {
+            Ok(Update::ReactionCount(count.into()))
+        } else if let Some(member) = from.chat_member {
+            Ok(Update::ChatMember(member.into()))
+        } else if let Some(member) = from.my_chat_member {
+            Ok(Update::MyChatMember(member.into()))
         } else {
             Err(())
         }
@@ -9,6 +9,19 @@ use serde::Deserialize;
 use std::pin::Pin;
 use std::{collections::VecDeque, convert::TryInto};
 
+//Telegram leaves out reactions unless they're asked for explicitly
+const ALLOWED_UPDATES: &[&str] = &[
+    "message",
+    "edited_message",
+    "channel_post",
+    "edited_channel_post",
+    "callback_query",
+    "message_reaction",
+    "message_reaction_count",
+    "chat_member",
+    "my_chat_member",
+];
+
 #[derive(Debug, Deserialize)]
 struct ApiResponse {
     #[serde(rename = "result")]
@@ -40,7 +53,11 @@ impl<'a> UpdateStream<'a> {
 
     //TODO return proper error type
     async fn get_poll_future(client: &'a Client, url: Url, offset: u64) -> Result<ApiResponse, ()> {
-        let json = serde_json::json!({"offset": offset, "timeout": 6000});
+        let json = serde_json::json!({
+            "offset": offset,
+            "timeout": 6000,
+            "allowed_updates": ALLOWED_UPDATES,
+        });
         let response = client.get(url).json(&json).send().await.unwrap();
 
         response.json::<ApiResponse>().map_err(|_| ()).await
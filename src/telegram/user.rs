@@ -1,4 +1,6 @@
+use super::{chat::Chat, ApiChatMemberUpdated};
 use serde::{Deserialize, Serialize};
+use std::convert::From;
 use std::fmt;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -21,3 +23,61 @@ impl fmt::Display for User {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberStatus {
+    Creator,
+    Administrator,
+    Member,
+    Restricted,
+    Left,
+    Kicked,
+}
+
+impl MemberStatus {
+    pub fn is_admin(self) -> bool {
+        matches!(self, MemberStatus::Creator | MemberStatus::Administrator)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatMember {
+    pub user: User,
+    pub status: MemberStatus,
+    //Only sent for restricted users, who may or may not be in the chat
+    pub is_member: Option<bool>,
+}
+
+impl ChatMember {
+    pub fn is_present(&self) -> bool {
+        match self.status {
+            MemberStatus::Left | MemberStatus::Kicked => false,
+            MemberStatus::Restricted => self.is_member.unwrap_or(true),
+            _ => true,
+        }
+    }
+}
+
+//The status of a member changed, like them joining, leaving or getting promoted
+#[derive(Debug)]
+pub struct ChatMemberUpdated {
+    pub chat: Chat,
+    //Who made the change, which is the member themselves when joining or leaving
+    pub from: User,
+    pub date: i64,
+    pub old: ChatMember,
+    pub new: ChatMember,
+}
+
+impl From<ApiChatMemberUpdated> for ChatMemberUpdated {
+    fn from(update: ApiChatMemberUpdated) -> Self {
+        Self {
+            chat: update.chat.into(),
+            from: update.from,
+            date: update.date,
+            old: update.old_chat_member,
+            new: update.new_chat_member,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+pub mod stopwords;
+
+pub use stopwords::StopWords;
+
+//Characters which are never part of the end of a link or mention, mostly sentence punctuation
+const TRAILING_PUNCTUATION: &[char] = &[
+    '.', ',', ';', ':', '!', '?', '\'', '"', ')', ']', '}', '>', '»', '”', '’',
+];
+
+//Splits a message into words suitable for word statistics and markov chains.
+//Links, @mentions and #hashtags are kept as single words, emoji are kept as their own words,
+//everything else is split on Unicode word boundaries with punctuation thrown away and case folded.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    for chunk in text.split_whitespace() {
+        if is_link(chunk) {
+            output.push(trim_link(chunk).to_string());
+        } else if let Some(tag) = mention_or_hashtag(chunk) {
+            output.push(fold_case(tag));
+        } else {
+            push_words(chunk, &mut output);
+        }
+    }
+    output
+}
+
+fn push_words(chunk: &str, output: &mut Vec<String>) {
+    for segment in chunk.split_word_bounds() {
+        //Keycap emoji like 1️⃣ start with an alphanumeric character
+        if is_emoji(segment) {
+            output.push(normalize_emoji(segment));
+        } else if segment.chars().any(char::is_alphanumeric) {
+            //Typographic apostrophes would otherwise make "don’t" and "don't" different words
+            output.push(fold_case(&segment.replace('’', "'")));
+        } else {
+            //Emoji sequences get their own segments, everything else left here is punctuation
+            for grapheme in segment.graphemes(true) {
+                if is_emoji(grapheme) {
+                    output.push(normalize_emoji(grapheme));
+                }
+            }
+        }
+    }
+}
+
+pub fn is_link(word: &str) -> bool {
+    let lower = word.get(..8).unwrap_or(word).to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+//Strips punctuation which most likely belongs to the sentence and not the link itself
+fn trim_link(link: &str) -> &str {
+    let mut trimmed = link;
+    while let Some(c) = trimmed.chars().last() {
+        //Keep closing parentheses which are part of the link, like wikipedia links tend to have
+        if c == ')' && trimmed.matches('(').count() >= trimmed.matches(')').count() {
+            break;
+        }
+        if !TRAILING_PUNCTUATION.contains(&c) {
+            break;
+        }
+        trimmed = &trimmed[..trimmed.len() - c.len_utf8()];
+    }
+    trimmed
+}
+
+//Returns the mention or hashtag without trailing punctuation if the word is one
+fn mention_or_hashtag(word: &str) -> Option<&str> {
+    if !word.starts_with('@') && !word.starts_with('#') {
+        return None;
+    }
+
+    let trimmed = word.trim_end_matches(TRAILING_PUNCTUATION);
+    let name = &trimmed[1..];
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        Some(trimmed)
+    } else {
+        None
+    }
+}
+
+//Full Unicode case folding. char::to_lowercase already covers nearly everything except the
+//characters which fold to something else than their lowercase form.
+pub fn fold_case(word: &str) -> String {
+    let mut output = String::with_capacity(word.len());
+    for c in word.chars() {
+        match c {
+            'ß' | 'ẞ' => output.push_str("ss"),
+            'ς' => output.push('σ'),
+            'ſ' => output.push('s'),
+            'ŉ' => output.push_str("ʼn"),
+            'ﬀ' => output.push_str("ff"),
+            'ﬁ' => output.push_str("fi"),
+            'ﬂ' => output.push_str("fl"),
+            'ﬃ' => output.push_str("ffi"),
+            'ﬄ' => output.push_str("ffl"),
+            'ﬅ' | 'ﬆ' => output.push_str("st"),
+            _ => output.extend(c.to_lowercase()),
+        }
+    }
+    output
+}
+
+//Remove the emoji presentation selector so that ❤ and ❤️ count as the same emoji
+pub fn normalize_emoji(emoji: &str) -> String {
+    emoji.chars().filter(|c| *c != '\u{FE0F}').collect()
+}
+
+//Checks if a grapheme cluster is an emoji. Not a complete implementation of the Unicode emoji
+//properties, but covers the blocks emoji are actually picked from.
+pub fn is_emoji(grapheme: &str) -> bool {
+    let first = match grapheme.chars().next() {
+        Some(c) => c as u32,
+        None => return false,
+    };
+
+    //Explicit emoji presentation or keycap sequences like 1️⃣
+    if grapheme.contains('\u{FE0F}') || grapheme.contains('\u{20E3}') {
+        return true;
+    }
+
+    matches!(first,
+        0x1F000..=0x1F0FF // Mahjong, domino and playing cards
+        | 0x1F18E // 🆎
+        | 0x1F191..=0x1F19A // Squared letters like 🆗
+        | 0x1F1E6..=0x1F1FF // Regional indicators, used for flags
+        | 0x1F200..=0x1F2FF // Enclosed ideographic supplement
+        | 0x1F300..=0x1F5FF // Misc symbols and pictographs
+        | 0x1F600..=0x1F64F // Emoticons
+        | 0x1F680..=0x1F6FF // Transport and map symbols
+        | 0x1F7E0..=0x1F7FF // Coloured circles and squares
+        | 0x1F900..=0x1FAFF // Supplemental symbols and pictographs
+        | 0x2600..=0x27BF // Misc symbols and dingbats
+        | 0x231A..=0x231B
+        | 0x23E9..=0x23F3
+        | 0x23F8..=0x23FA
+        | 0x2B05..=0x2B07
+        | 0x2B1B..=0x2B1C
+        | 0x2B50
+        | 0x2B55
+        | 0x3030
+        | 0x303D
+        | 0x3297
+        | 0x3299
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_words_and_folds_case() {
+        assert_eq!(tokenize("Hello, WORLD!"), ["hello", "world"]);
+        assert_eq!(tokenize("Straße"), ["strasse"]);
+    }
+
+    #[test]
+    fn keeps_apostrophes_the_same() {
+        assert_eq!(tokenize("Don’t"), tokenize("don't"));
+    }
+
+    #[test]
+    fn keeps_links_whole() {
+        assert_eq!(
+            tokenize("see https://example.com/a?b=C."),
+            ["see", "https://example.com/a?b=C"]
+        );
+        assert_eq!(
+            tokenize("https://en.wikipedia.org/wiki/Rust_(language)."),
+            ["https://en.wikipedia.org/wiki/Rust_(language)"]
+        );
+    }
+
+    #[test]
+    fn keeps_mentions_and_hashtags() {
+        assert_eq!(tokenize("@Some_User #Rust!"), ["@some_user", "#rust"]);
+        assert_eq!(tokenize("#"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn splits_emoji_into_words() {
+        assert_eq!(tokenize("nice👍👍"), ["nice", "👍", "👍"]);
+        assert_eq!(tokenize("❤️"), tokenize("❤"));
+        assert_eq!(tokenize("1️⃣"), ["1⃣"]);
+    }
+}
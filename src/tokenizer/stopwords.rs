@@ -0,0 +1,70 @@
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+
+macro_rules! include_stopwords {
+    ($s:tt) => {
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/stopwords/",
+            $s,
+            ".txt"
+        ))
+    };
+}
+
+//Language code and its stop word list, one word per line
+const LISTS: &[(&str, &str)] = &[
+    ("da", include_stopwords!("da")),
+    ("de", include_stopwords!("de")),
+    ("en", include_stopwords!("en")),
+    ("no", include_stopwords!("no")),
+    ("sv", include_stopwords!("sv")),
+];
+
+lazy_static! {
+    static ref PARSED: HashMap<&'static str, HashSet<&'static str>> = LISTS
+        .iter()
+        .map(|(lang, list)| (*lang, list.lines().filter(|l| !l.is_empty()).collect()))
+        .collect();
+}
+
+pub fn languages() -> impl Iterator<Item = &'static str> {
+    LISTS.iter().map(|(lang, _)| *lang)
+}
+
+pub fn is_supported(language: &str) -> bool {
+    PARSED.contains_key(language)
+}
+
+//The combined stop words of a set of languages
+#[derive(Default)]
+pub struct StopWords {
+    lists: Vec<&'static HashSet<&'static str>>,
+}
+
+impl StopWords {
+    //Unknown languages are ignored
+    pub fn for_languages<'a, I>(languages: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        Self {
+            lists: languages
+                .into_iter()
+                .filter_map(|lang| PARSED.get(lang))
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.lists.iter().any(|list| list.contains(word))
+    }
+
+    //Every stop word, for leaving them out in queries
+    pub fn words(&self) -> Vec<&'static str> {
+        self.lists
+            .iter()
+            .flat_map(|list| list.iter().copied())
+            .collect()
+    }
+}
@@ -1,8 +1,20 @@
-use crate::include_sql;
-use crate::telegram::{user::User, Telegram};
+use crate::{
+    image,
+    telegram::{
+        chat::{Chat, ChatType},
+        message::Message,
+        user::User,
+        ChatMemberError, Telegram,
+    },
+};
 use chrono::Duration;
 use deadpool_postgres::Pool;
+use futures::StreamExt;
 use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use tokio::task;
 
 #[macro_export]
 macro_rules! include_sql {
@@ -17,28 +29,184 @@ macro_rules! params {
         &[$(&$x),+]
     };
 }
-//Will get the user from cache if it is cached, otherwise request the data
+//Parallel getChatMember requests made when resolving users
+const USER_LOOKUPS: usize = 8;
+
+//A user as shown in a chat, marked when they aren't in it anymore
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChatUser {
+    pub user: User,
+    pub left: bool,
+}
+
+impl fmt::Display for ChatUser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.left {
+            write!(f, "{} (left)", self.user)
+        } else {
+            write!(f, "{}", self.user)
+        }
+    }
+}
+
+fn unknown_user(user_id: i64) -> User {
+    User {
+        id: user_id,
+        first_name: format!("Unknown user {}", user_id),
+        is_bot: false,
+        last_name: None,
+        username: None,
+    }
+}
+
+//Resolves a single user, see get_users
 pub async fn get_user(
     chat_id: i64,
     user_id: i64,
     telegram: &Telegram,
-    config: &crate::Config,
+    context: &crate::Context,
     redis: &mut darkredis::Connection,
-) -> User {
-    let user_path = format!("tg.user.{}.{}", chat_id, user_id);
-    match redis.get(&user_path).await.unwrap() {
-        Some(u) => rmp_serde::from_slice(&u).unwrap(),
-        None => {
-            debug!("Getting user from telegram");
-            let user = telegram.get_chat_member(chat_id, user_id).await.unwrap();
-            let serialized = rmp_serde::to_vec(&user).unwrap();
-            redis
-                .set_and_expire_seconds(&user_path, &serialized, config.cache.username as u32)
-                .await
-                .unwrap();
-            user
+) -> ChatUser {
+    get_users(chat_id, &[user_id], telegram, context, redis)
+        .await
+        .remove(&user_id)
+        .unwrap()
+}
+
+//Resolves users from the cache, then the last data seen of them in the chat, and only asks
+//Telegram about the ones left over. Users Telegram doesn't know about are cached as well, but
+//users it couldn't be asked about aren't.
+pub async fn get_users(
+    chat_id: i64,
+    user_ids: &[i64],
+    telegram: &Telegram,
+    context: &crate::Context,
+    redis: &mut darkredis::Connection,
+) -> HashMap<i64, ChatUser> {
+    let mut users = HashMap::new();
+    let mut ids: Vec<i64> = user_ids.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.is_empty() {
+        return users;
+    }
+
+    let key = |id: i64| format!("tg.user.{}.{}", chat_id, id);
+    let keys: Vec<String> = ids.iter().map(|id| key(*id)).collect();
+    match redis.mget(&keys).await {
+        Ok(cached) => {
+            for (id, value) in ids.iter().zip(cached) {
+                //Entries in an older format are treated as missing
+                if let Some(user) = value.and_then(|v| rmp_serde::from_slice(&v).ok()) {
+                    users.insert(*id, user);
+                }
+            }
         }
+        Err(e) => error!("Failed to get cached users in {}: {:?}", chat_id, e),
     }
+    let mut missing: Vec<i64> = ids
+        .into_iter()
+        .filter(|id| !users.contains_key(id))
+        .collect();
+    let mut resolved: Vec<ChatUser> = Vec::new();
+
+    if !missing.is_empty() {
+        let left = left_users(chat_id, &context.db_pool)
+            .await
+            .unwrap_or_else(|e| {
+                error!("{}", e);
+                HashSet::new()
+            });
+        let conn = context.db_pool.get().await.unwrap();
+        match conn
+            .query(
+                include_sql!("members/getlastdata.sql"),
+                params![chat_id, missing],
+            )
+            .await
+        {
+            Ok(rows) => {
+                for row in rows {
+                    let id: i64 = row.get(0);
+                    resolved.push(ChatUser {
+                        user: User {
+                            id,
+                            first_name: row.get(1),
+                            is_bot: false,
+                            last_name: row.get(2),
+                            username: row.get(3),
+                        },
+                        left: left.contains(&id),
+                    });
+                }
+            }
+            Err(e) => error!("Failed to get last user data in {}: {:?}", chat_id, e),
+        }
+        missing.retain(|id| !resolved.iter().any(|u| u.user.id == *id));
+    }
+
+    if !missing.is_empty() {
+        debug!("Getting {} user(s) from telegram", missing.len());
+        //Along with whether Telegram gave an answer, failed lookups might work next time
+        let looked_up: Vec<(ChatUser, bool)> = futures::stream::iter(missing)
+            .map(|id| async move {
+                match telegram.get_chat_member(chat_id, id).await {
+                    Ok(member) => (
+                        ChatUser {
+                            left: !member.is_present(),
+                            user: member.user,
+                        },
+                        true,
+                    ),
+                    Err(ChatMemberError::NotFound) => {
+                        warn!("Couldn't find user {} in chat {}", id, chat_id);
+                        (
+                            ChatUser {
+                                user: unknown_user(id),
+                                left: true,
+                            },
+                            true,
+                        )
+                    }
+                    Err(ChatMemberError::Failed(e)) => {
+                        warn!("Couldn't get user {} in chat {}: {}", id, chat_id, e);
+                        (
+                            ChatUser {
+                                user: unknown_user(id),
+                                left: false,
+                            },
+                            false,
+                        )
+                    }
+                }
+            })
+            .buffer_unordered(USER_LOOKUPS)
+            .collect()
+            .await;
+        for (user, answered) in looked_up {
+            if answered {
+                resolved.push(user);
+            } else {
+                users.insert(user.user.id, user);
+            }
+        }
+    }
+
+    for user in resolved {
+        let serialized = rmp_serde::to_vec(&user).unwrap();
+        if let Err(e) = redis
+            .set_and_expire_seconds(
+                key(user.user.id),
+                &serialized,
+                context.config.cache.username as u32,
+            )
+            .await
+        {
+            error!("Failed to cache user {}: {:?}", user.user.id, e);
+        }
+        users.insert(user.user.id, user);
+    }
+    users
 }
 
 pub fn parse_time(input: &[String]) -> Option<Duration> {
@@ -69,21 +237,277 @@ pub fn parse_time(input: &[String]) -> Option<Duration> {
     }
 }
 
-//Returns the last known user id matching name in chat_id
-//If multiple users match, it will pick one at complete random due to how SQLite works
-pub async fn get_user_id(chat_id: i64, name: &str, pool: &Pool) -> Option<i64> {
+//Command arguments split into positional arguments and --flags. A flag takes every argument
+//after it until the next flag, so `--since 2 weeks` gives the flag since the values [2, weeks].
+pub struct Arguments {
+    pub positional: Vec<String>,
+    //Name, where the flag is in args, and its values
+    flags: Vec<(String, usize, Vec<String>)>,
+}
+
+impl Arguments {
+    //Expects the command itself to have been removed from args
+    pub fn parse(args: &[String]) -> Self {
+        let mut positional = Vec::new();
+        let mut flags: Vec<(String, usize, Vec<String>)> = Vec::new();
+        for (index, arg) in args.iter().enumerate() {
+            if arg.len() > 2 && arg.starts_with("--") {
+                flags.push((arg[2..].to_lowercase(), index, Vec::new()));
+            } else if let Some((_, _, values)) = flags.last_mut() {
+                values.push(arg.clone());
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Self { positional, flags }
+    }
+
+    pub fn flag(&self, name: &str) -> Option<&[String]> {
+        self.flags
+            .iter()
+            .find(|(flag, _, _)| flag == name)
+            .map(|(_, _, values)| values.as_slice())
+    }
+
+    //Where the first value of a flag is in args
+    pub fn flag_position(&self, name: &str) -> Option<usize> {
+        self.flags
+            .iter()
+            .find(|(flag, _, _)| flag == name)
+            .map(|(_, index, _)| index + 1)
+    }
+
+    //Returns the first flag which isn't in known
+    pub fn unknown_flag(&self, known: &[&str]) -> Option<&str> {
+        self.flags
+            .iter()
+            .map(|(flag, _, _)| flag.as_str())
+            .find(|flag| !known.contains(flag))
+    }
+}
+
+//Every Redis key which belongs to a chat, found by the chat id being one of its parts
+pub async fn chat_redis_keys(chat_id: i64, redis: &mut darkredis::Connection) -> Vec<String> {
+    let pattern = format!("tg.*{}*", chat_id);
+    let keys: Vec<Vec<u8>> = redis.scan().pattern(&pattern).run().collect().await;
+    let id = chat_id.to_string();
+    keys.into_iter()
+        .map(|k| String::from_utf8_lossy(&k).into_owned())
+        //The pattern also matches longer ids which contain this one
+        .filter(|k| k.split(['.', ':']).any(|part| part == id))
+        .collect()
+}
+
+//Users who have left the chat and haven't come back
+pub async fn left_users(chat_id: i64, pool: &Pool) -> Result<HashSet<i64>, String> {
+    let conn = pool.get().await.unwrap();
+    conn.query(include_sql!("members/getleft.sql"), params![chat_id])
+        .await
+        .map(|rows| rows.into_iter().map(|r| r.get(0)).collect())
+        .map_err(|e| format!("getting users who left: {:?}", e))
+}
+
+//Messages in the General topic of a forum don't carry a thread id, but it is 1 when sending
+pub const GENERAL_TOPIC: i64 = 1;
+
+//Reads the topic statistics should be limited to from --topic [<name>]. Without a name it's the
+//topic the command was sent in. The error is meant to be shown to the user.
+pub async fn topic_scope(
+    msg: &Message,
+    arguments: &Arguments,
+    pool: &Pool,
+) -> Result<Option<i64>, String> {
+    let name = match arguments.flag("topic") {
+        None => return Ok(None),
+        Some([]) => return Ok(Some(msg.thread_id.unwrap_or(GENERAL_TOPIC))),
+        Some(words) => words.join(" "),
+    };
+    if name.eq_ignore_ascii_case("general") {
+        return Ok(Some(GENERAL_TOPIC));
+    }
+
     let conn = pool.get().await.unwrap();
-    conn.query_opt(
-        include_sql!("getuseridfromname.sql"),
-        params![chat_id, name],
+    //Topics can share a name, thread ids grow over time so the first is the newest one
+    conn.query(
+        include_sql!("topics/getbyname.sql"),
+        params![msg.chat.id, name],
     )
     .await
-    .unwrap()
-    .map(|r| r.get(0))
+    .map_err(|e| {
+        error!("Failed to get topic {} in {}: {:?}", name, msg.chat.id, e);
+        format!("Couldn't look up the topic {}, try again later", name)
+    })?
+    .first()
+    .map(|r| Some(r.get(0)))
+    .ok_or_else(|| format!("I don't know of a topic named {}", name))
+}
+
+//Link to a message which members of the chat can open. Only supergroups have these.
+pub fn message_link(chat: &Chat, message_id: i64) -> Option<String> {
+    match chat.kind {
+        ChatType::SuperGroup { .. } => {
+            //Supergroup ids are the internal channel id prefixed with -100
+            let id = chat.id.to_string();
+            id.strip_prefix("-100")
+                .map(|internal| format!("https://t.me/c/{}/{}", internal, message_id))
+        }
+        _ => None,
+    }
 }
 
-pub fn seconds_to_hours(seconds: i32) -> f64 {
-    f64::from(seconds) / (60.0 * 60.0)
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+//Inline keyboard to flip through pages, where each button's callback data is prefix:page
+pub fn page_buttons(prefix: &str, page: i64, pages: i64) -> serde_json::Value {
+    let mut row = Vec::new();
+    if page > 0 {
+        row.push(serde_json::json!({
+            "text": "◀ Previous",
+            "callback_data": format!("{}:{}", prefix, page - 1)
+        }));
+    }
+    if page + 1 < pages {
+        row.push(serde_json::json!({
+            "text": "Next ▶",
+            "callback_data": format!("{}:{}", prefix, page + 1)
+        }));
+    }
+    serde_json::json!({ "inline_keyboard": [row] })
+}
+
+//How a name given as a command argument matches one of a user's names, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NameMatch {
+    Username,
+    Name,
+    Prefix,
+    Substring,
+    //Edit distance to the closest name
+    Similar(usize),
+}
+
+pub enum UserMatch {
+    Found(i64),
+    //Several users match equally well
+    Ambiguous(Vec<i64>),
+    NotFound,
+}
+
+//Prefix for an argument naming a user by id, like the ones a disambiguation keyboard makes
+pub const USER_ID_PREFIX: char = '#';
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(previous + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn match_name(
+    query: &str,
+    first_name: &str,
+    last_name: Option<&str>,
+    username: Option<&str>,
+) -> Option<NameMatch> {
+    let username = username.map(str::to_lowercase);
+    let first_name = first_name.to_lowercase();
+    let full_name = last_name.map(|l| format!("{} {}", first_name, l.to_lowercase()));
+    let names: Vec<&str> = username
+        .iter()
+        .chain(std::iter::once(&first_name))
+        .chain(full_name.iter())
+        .map(String::as_str)
+        .collect();
+
+    if username.as_deref() == Some(query) {
+        Some(NameMatch::Username)
+    } else if first_name == query || full_name.as_deref() == Some(query) {
+        Some(NameMatch::Name)
+    } else if names.iter().any(|n| n.starts_with(query)) {
+        Some(NameMatch::Prefix)
+    } else if names.iter().any(|n| n.contains(query)) {
+        Some(NameMatch::Substring)
+    } else {
+        //Allow about one typo for every three characters
+        let distance = names.iter().map(|n| edit_distance(query, n)).min()?;
+        (distance <= (query.chars().count() / 3).max(1)).then_some(NameMatch::Similar(distance))
+    }
+}
+
+//Finds the user a name refers to by their current and earlier names in chat_id. Matches are
+//ranked by exact username, exact name, prefix, substring and then edit distance, with current
+//names winning over old ones. Ties are left for the caller to ask about.
+pub async fn find_user(chat_id: i64, name: &str, pool: &Pool) -> UserMatch {
+    let conn = pool.get().await.unwrap();
+    let rows = match conn
+        .query(include_sql!("members/getnames.sql"), params![chat_id])
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Failed to get user names in {}: {:?}", chat_id, e);
+            return UserMatch::NotFound;
+        }
+    };
+
+    if let Some(id) = name
+        .strip_prefix(USER_ID_PREFIX)
+        .and_then(|id| id.parse::<i64>().ok())
+    {
+        return if rows.iter().any(|r| r.get::<_, i64>(0) == id) {
+            UserMatch::Found(id)
+        } else {
+            UserMatch::NotFound
+        };
+    }
+
+    let query = name.trim().trim_start_matches('@').to_lowercase();
+    if query.is_empty() {
+        return UserMatch::NotFound;
+    }
+    //Best match of every user, where false sorts current names first
+    let mut best: HashMap<i64, (NameMatch, bool)> = HashMap::new();
+    for row in rows {
+        let m = match match_name(&query, row.get(1), row.get(2), row.get(3)) {
+            Some(m) => (m, !row.get::<_, bool>(4)),
+            None => continue,
+        };
+        best.entry(row.get(0))
+            .and_modify(|b| *b = (*b).min(m))
+            .or_insert(m);
+    }
+
+    let top = match best.values().min() {
+        Some(t) => *t,
+        None => return UserMatch::NotFound,
+    };
+    let mut tied: Vec<i64> = best
+        .into_iter()
+        .filter(|(_, m)| *m == top)
+        .map(|(id, _)| id)
+        .collect();
+    if tied.len() == 1 {
+        UserMatch::Found(tied[0])
+    } else {
+        tied.sort_unstable();
+        UserMatch::Ambiguous(tied)
+    }
+}
+
+pub fn seconds_to_hours(seconds: i64) -> f64 {
+    seconds as f64 / (60.0 * 60.0)
 }
 
 pub unsafe fn rgba_to_cairo(mut ptr: *mut u8, len: usize) {
@@ -150,6 +574,46 @@ pub async fn calculate_sticker_hash(
     }
 }
 
+//Perceptual hash of a sticker or photo, cached like calculate_sticker_hash.
+//Returns None for files which aren't still images, like animated stickers.
+pub async fn calculate_perceptual_hash(
+    telegram: &Telegram,
+    redis: &mut darkredis::Connection,
+    file_id: &str,
+) -> Option<i64> {
+    let command = darkredis::Command::new("HGET")
+        .arg(b"tg.perceptualhashes")
+        .arg(&file_id);
+    let cached = redis
+        .run_command(command)
+        .await
+        .map_err(|e| error!("Couldn't get data from hash key: {}", e))
+        .ok()?
+        .optional_string();
+    if let Some(hash) = cached {
+        return String::from_utf8(hash).ok()?.parse().ok();
+    }
+
+    let file = telegram.download_file(redis, file_id).await.ok()?;
+    let hash = match task::block_in_place(|| image::perceptual_hash(&file)) {
+        Ok(h) => h,
+        Err(e) => {
+            debug!("No perceptual hash for {}: {}", file_id, e);
+            return None;
+        }
+    };
+
+    let value = hash.to_string();
+    let command = darkredis::Command::new("HSET")
+        .arg(b"tg.perceptualhashes")
+        .arg(&file_id)
+        .arg(&value);
+    if let Err(e) = redis.run_command(command).await {
+        error!("Couldn't set perceptual hash: {}", e);
+    }
+    Some(hash)
+}
+
 //Align a each line after a symbol
 pub fn align_text_after(symbol: char, text: String) -> String {
     let mut left_len = 0; //Length needed on left side